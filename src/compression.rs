@@ -0,0 +1,344 @@
+//! 响应压缩
+//!
+//! 基于 tower-http 的 `CompressionLayer`，根据 `Accept-Encoding` 协商 zstd / br / gzip
+//! （权重相同时按此顺序优先）。
+//! 只压缩 MIME 白名单内、超过最小大小的响应；已带 `Content-Encoding`（如 S3 中以 gzip 存储的对象）
+//! 和 206 部分内容响应不会被再次压缩。可压缩的响应总会带上 `Vary: Accept-Encoding`。
+//! 压缩后的响应与原始内容字节不同，强 ETag 被改为弱 ETag（`W/"..."`），
+//! `If-None-Match` 仍能按弱比较命中，`If-Range` 不会把压缩后的内容当作同一表示。
+
+use crate::config::CompressionConfig;
+use axum::body::HttpBody;
+use axum::http::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG};
+use axum::http::{HeaderValue, Response, StatusCode};
+use axum::middleware::map_response;
+use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::predicate::Predicate;
+
+/// 默认可压缩的 MIME 类型，`type/*` 匹配该类型下的所有子类型
+pub const DEFAULT_COMPRESSIBLE_TYPES: &[&str] = &[
+    "text/*",
+    "application/javascript",
+    "application/x-javascript",
+    "application/json",
+    "application/manifest+json",
+    "application/ld+json",
+    "application/xml",
+    "image/svg+xml",
+    "application/wasm",
+];
+
+/// 压缩判断条件：状态码、MIME 白名单和最小大小
+#[derive(Clone, Debug)]
+pub struct CompressiblePredicate {
+    min_size: u64,
+    mime_types: Arc<[String]>,
+}
+
+impl CompressiblePredicate {
+    pub fn new(config: &CompressionConfig) -> Self {
+        Self {
+            min_size: config.min_size,
+            mime_types: config.mime_types.iter().cloned().collect(),
+        }
+    }
+
+    /// 判断 Content-Type 是否在白名单内（忽略参数和大小写）
+    pub fn is_compressible_type(&self, content_type: &str) -> bool {
+        is_compressible_type(&self.mime_types, content_type)
+    }
+}
+
+/// 判断 Content-Type 是否匹配 MIME 白名单（忽略参数和大小写）
+///
+/// 白名单中 `type/*` 匹配该类型下的所有子类型，其余需要完全相等。
+pub fn is_compressible_type(mime_types: &[impl AsRef<str>], content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let Some((top_level, _)) = essence.split_once('/') else {
+        return false;
+    };
+    mime_types.iter().any(|pattern| {
+        let pattern = pattern.as_ref();
+        match pattern.strip_suffix("/*") {
+            Some(pattern_top) => pattern_top.eq_ignore_ascii_case(top_level),
+            None => pattern.eq_ignore_ascii_case(&essence),
+        }
+    })
+}
+
+impl Predicate for CompressiblePredicate {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: HttpBody,
+    {
+        let status = response.status();
+        if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::PARTIAL_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            return false;
+        }
+
+        let headers = response.headers();
+        let compressible = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| self.is_compressible_type(v));
+        if !compressible {
+            return false;
+        }
+
+        // 大小未知（流式响应没有 Content-Length）时压缩
+        let size = response.body().size_hint().exact().or_else(|| {
+            headers
+                .get(CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+        });
+        size.is_none_or(|size| size >= self.min_size)
+    }
+}
+
+/// 根据配置创建压缩层，未启用时返回 `None`
+pub fn compression_layer(
+    config: &CompressionConfig,
+) -> Option<CompressionLayer<CompressiblePredicate>> {
+    config.enabled.then(|| {
+        CompressionLayer::new()
+            .br(config.br)
+            .zstd(config.zstd)
+            .gzip(config.gzip)
+            .no_deflate()
+            .compress_when(CompressiblePredicate::new(config))
+    })
+}
+
+/// 为路由添加压缩层（未启用时原样返回），压缩后的响应使用弱 ETag
+pub fn compress(router: axum::Router, config: &CompressionConfig) -> axum::Router {
+    let Some(layer) = compression_layer(config) else {
+        return router;
+    };
+    router
+        .layer(map_response(mark_identity))
+        .layer(layer)
+        .layer(map_response(weaken_etag))
+}
+
+/// 标记进入压缩层之前没有 `Content-Encoding` 的响应
+#[derive(Clone, Copy)]
+struct IdentityEncoding;
+
+async fn mark_identity<B>(mut response: Response<B>) -> Response<B> {
+    if !response.headers().contains_key(CONTENT_ENCODING) {
+        response.extensions_mut().insert(IdentityEncoding);
+    }
+    response
+}
+
+/// 压缩层添加了 `Content-Encoding` 时把强 ETag 改为弱 ETag
+async fn weaken_etag<B>(mut response: Response<B>) -> Response<B> {
+    let identity = response
+        .extensions_mut()
+        .remove::<IdentityEncoding>()
+        .is_some();
+    if !identity || !response.headers().contains_key(CONTENT_ENCODING) {
+        return response;
+    }
+    let weak = response
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .and_then(|etag| HeaderValue::from_str(&format!("W/{etag}")).ok());
+    if let Some(weak) = weak {
+        response.headers_mut().insert(ETAG, weak);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::{ACCEPT_ENCODING, VARY};
+    use axum::routing::get;
+    use axum_test::TestServer;
+
+    fn server(config: &CompressionConfig) -> TestServer {
+        let big = "x".repeat(4096);
+        let router = axum::Router::new()
+            .route(
+                "/app.js",
+                get(move || async move {
+                    (
+                        [(CONTENT_TYPE, "application/javascript"), (ETAG, "\"v1\"")],
+                        big,
+                    )
+                }),
+            )
+            .route(
+                "/partial.js",
+                get(|| async {
+                    (
+                        StatusCode::PARTIAL_CONTENT,
+                        [(CONTENT_TYPE, "application/javascript"), (ETAG, "\"v1\"")],
+                        "x".repeat(4096),
+                    )
+                }),
+            )
+            .route(
+                "/multipart",
+                get(|| async {
+                    (
+                        StatusCode::PARTIAL_CONTENT,
+                        [(CONTENT_TYPE, "multipart/byteranges; boundary=b")],
+                        "x".repeat(4096),
+                    )
+                }),
+            )
+            .route(
+                "/small.css",
+                get(|| async { ([(CONTENT_TYPE, "text/css; charset=utf-8")], "a{}") }),
+            )
+            .route(
+                "/image.png",
+                get(|| async { ([(CONTENT_TYPE, "image/png")], vec![0u8; 4096]) }),
+            )
+            .route(
+                "/stored.js",
+                get(|| async {
+                    (
+                        [
+                            (CONTENT_TYPE, "application/javascript"),
+                            (CONTENT_ENCODING, "gzip"),
+                            (ETAG, "\"stored\""),
+                        ],
+                        vec![0u8; 4096],
+                    )
+                }),
+            );
+        TestServer::new(compress(router, config)).unwrap()
+    }
+
+    /// 测试 MIME 白名单匹配
+    ///
+    /// 验证：
+    /// - `text/*` 通配匹配所有文本类型
+    /// - 忽略参数和大小写
+    /// - 不在白名单内的类型不压缩
+    #[test]
+    fn test_is_compressible_type() {
+        let predicate = CompressiblePredicate::new(&CompressionConfig::default());
+        assert!(predicate.is_compressible_type("text/html; charset=utf-8"));
+        assert!(predicate.is_compressible_type("Application/JavaScript"));
+        assert!(predicate.is_compressible_type("image/svg+xml"));
+        assert!(predicate.is_compressible_type("application/wasm"));
+        assert!(!predicate.is_compressible_type("image/png"));
+        assert!(!predicate.is_compressible_type("application/octet-stream"));
+        assert!(!predicate.is_compressible_type("text"));
+    }
+
+    /// 测试根据 Accept-Encoding 协商压缩
+    ///
+    /// 验证：
+    /// - 权重相同时按 zstd、br、gzip 的顺序选择
+    /// - 可压缩的响应即使未压缩也带 `Vary: Accept-Encoding`
+    #[tokio::test]
+    async fn test_negotiates_encoding() {
+        let server = server(&CompressionConfig::default());
+
+        let resp = server
+            .get("/app.js")
+            .add_header(ACCEPT_ENCODING, "gzip, br, zstd")
+            .await;
+        assert_eq!(resp.header(CONTENT_ENCODING), "zstd");
+        assert_eq!(resp.header(VARY), "accept-encoding");
+
+        let resp = server
+            .get("/app.js")
+            .add_header(ACCEPT_ENCODING, "gzip, br")
+            .await;
+        assert_eq!(resp.header(CONTENT_ENCODING), "br");
+
+        let resp = server
+            .get("/app.js")
+            .add_header(ACCEPT_ENCODING, "gzip")
+            .await;
+        assert_eq!(resp.header(CONTENT_ENCODING), "gzip");
+
+        let resp = server.get("/app.js").await;
+        assert!(resp.maybe_header(CONTENT_ENCODING).is_none());
+        assert_eq!(resp.header(VARY), "accept-encoding");
+        assert_eq!(resp.text().len(), 4096);
+    }
+
+    /// 测试压缩后的响应使用弱 ETag
+    ///
+    /// 验证：
+    /// - 压缩后的 ETag 与未压缩的 ETag 不同，且为弱 ETag
+    /// - 未压缩的响应和存储中已编码的对象保留原来的强 ETag
+    #[tokio::test]
+    async fn test_weakens_compressed_etag() {
+        let server = server(&CompressionConfig::default());
+
+        let identity = server.get("/app.js").await;
+        assert_eq!(identity.header(ETAG), "\"v1\"");
+
+        let compressed = server
+            .get("/app.js")
+            .add_header(ACCEPT_ENCODING, "gzip")
+            .await;
+        assert_eq!(compressed.header(CONTENT_ENCODING), "gzip");
+        assert_eq!(compressed.header(ETAG), "W/\"v1\"");
+        assert_ne!(compressed.header(ETAG), identity.header(ETAG));
+
+        let stored = server
+            .get("/stored.js")
+            .add_header(ACCEPT_ENCODING, "gzip")
+            .await;
+        assert_eq!(stored.header(ETAG), "\"stored\"");
+    }
+
+    /// 测试不压缩的情况
+    ///
+    /// 验证：
+    /// - 小于最小大小的响应不压缩
+    /// - 不在白名单内的类型不压缩
+    /// - 已带 Content-Encoding 的响应不会被再次压缩
+    /// - 206 响应（包括没有 Content-Range 的 multipart/byteranges）不压缩
+    #[tokio::test]
+    async fn test_skips_uncompressible_responses() {
+        let server = server(&CompressionConfig::default());
+
+        for path in ["/small.css", "/image.png"] {
+            let resp = server.get(path).add_header(ACCEPT_ENCODING, "gzip").await;
+            assert!(resp.maybe_header(CONTENT_ENCODING).is_none(), "{path}");
+            assert!(resp.maybe_header(VARY).is_none(), "{path}");
+        }
+
+        let resp = server
+            .get("/stored.js")
+            .add_header(ACCEPT_ENCODING, "br")
+            .await;
+        assert_eq!(resp.header(CONTENT_ENCODING), "gzip");
+        assert_eq!(resp.as_bytes().len(), 4096);
+
+        for path in ["/partial.js", "/multipart"] {
+            let resp = server.get(path).add_header(ACCEPT_ENCODING, "gzip").await;
+            resp.assert_status(StatusCode::PARTIAL_CONTENT);
+            assert!(resp.maybe_header(CONTENT_ENCODING).is_none(), "{path}");
+            assert_eq!(resp.as_bytes().len(), 4096, "{path}");
+        }
+        let resp = server
+            .get("/partial.js")
+            .add_header(ACCEPT_ENCODING, "gzip")
+            .await;
+        assert_eq!(resp.header(ETAG), "\"v1\"");
+    }
+}
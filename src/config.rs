@@ -0,0 +1,2210 @@
+//! 应用配置
+//!
+//! 配置来源优先级（从高到低）：命令行参数 > 环境变量 > 配置文件 > 默认值。
+//! 配置在启动时统一校验，错误通过 `ConfigError` 返回而不是 panic。
+
+use crate::auth::{Htpasswd, Jwks};
+use crate::compression::DEFAULT_COMPRESSIBLE_TYPES;
+use crate::handlers::files::{CACHE_MAX_AGE, DEFAULT_HASHED_FILENAME_PATTERN, NO_CACHE_EXTS};
+use crate::site::HostMatcher;
+use crate::storage::{CacheOptions, MAX_PRESIGNED_URL_EXPIRY};
+use axum::http::{HeaderValue, StatusCode};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 配置错误
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    /// 配置文件读取失败
+    #[error("Failed to read config file '{path}': {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// 配置文件解析失败
+    #[error("Failed to parse config file '{path}': {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    /// 配置值无效
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// 命令行参数
+///
+/// 每个参数都可以通过对应的环境变量设置，未设置时使用配置文件中的值。
+#[derive(Parser, Debug, Default)]
+#[command(
+    name = "static-server",
+    version,
+    about = "Serve static sites from S3 compatible storage"
+)]
+pub struct Cli {
+    /// 配置文件路径（TOML 格式）
+    #[arg(short, long, env = "STATIC_SERVER_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// 监听地址，如 0.0.0.0:3000
+    #[arg(long, env = "STATIC_SERVER_LISTEN")]
+    pub listen: Option<SocketAddr>,
+
+    /// 日志级别
+    #[arg(long, env = "STATIC_SERVER_LOG_LEVEL")]
+    pub log_level: Option<LogLevel>,
+
+    /// 日志格式
+    #[arg(long, env = "STATIC_SERVER_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+
+    /// S3 存储桶名称
+    #[arg(long, env = "AWS_BUCKET")]
+    pub bucket: Option<String>,
+
+    /// 本地目录，设置后代替 S3 存储桶提供文件
+    #[arg(long, env = "STATIC_ROOT")]
+    pub root: Option<PathBuf>,
+
+    /// 存储中静态文件的前缀
+    #[arg(long, env = "STATIC_SERVER_PREFIX")]
+    pub prefix: Option<String>,
+
+    /// 索引文件候选列表，按顺序尝试，逗号分隔
+    #[arg(long, env = "STATIC_SERVER_INDEX_FILES", value_delimiter = ',')]
+    pub index_files: Option<Vec<String>>,
+
+    /// 是否在站点根目录 `/` 返回索引文件（true/false）
+    #[arg(long, env = "STATIC_SERVER_SERVE_ROOT")]
+    pub serve_root: Option<bool>,
+
+    /// 是否启用 SPA 回退（true/false）
+    #[arg(long, env = "STATIC_SERVER_SPA_FALLBACK")]
+    pub spa_fallback: Option<bool>,
+
+    /// 未带内容哈希的可缓存文件的 max-age（秒）
+    #[arg(long, env = "STATIC_SERVER_CACHE_MAX_AGE")]
+    pub cache_max_age: Option<u64>,
+
+    /// 不缓存的文件扩展名，逗号分隔
+    #[arg(long, env = "STATIC_SERVER_NO_CACHE_EXTENSIONS", value_delimiter = ',')]
+    pub no_cache_extensions: Option<Vec<String>>,
+
+    /// 子命令，不指定时启动服务
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// 子命令
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 使用配置文件中的密钥生成签名 URL
+    Sign(SignArgs),
+}
+
+/// `sign` 子命令的参数
+#[derive(Args, Debug)]
+pub struct SignArgs {
+    /// 请求路径，如 /private/report.pdf
+    pub path: String,
+
+    /// 有效期（秒）
+    #[arg(long, default_value_t = 3600)]
+    pub expires_in: u64,
+
+    /// 签名密钥 ID，默认使用第一个密钥
+    #[arg(long)]
+    pub key_id: Option<String>,
+
+    /// 使用该虚拟主机的站点配置，默认使用顶层 `[site]`
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// 拼接在签名路径前的 URL，如 https://static.example.com
+    #[arg(long)]
+    pub base_url: Option<String>,
+}
+
+/// 日志级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    #[default]
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Trace => tracing::Level::TRACE,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Error => tracing::Level::ERROR,
+        }
+    }
+}
+
+/// 日志格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// 多行可读格式（适合开发）
+    #[default]
+    Pretty,
+    /// 单行紧凑格式
+    Compact,
+    /// JSON 格式（适合日志采集）
+    Json,
+}
+
+/// 应用配置
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// 监听地址
+    pub listen: SocketAddr,
+    /// 日志配置
+    pub log: LogConfig,
+    /// 存储配置
+    pub storage: StorageConfig,
+    /// 站点配置（默认站点，Host 头未匹配任何虚拟主机时使用）
+    pub site: SiteConfig,
+    /// 虚拟主机列表，按 Host 头选择站点
+    pub hosts: Vec<VirtualHostConfig>,
+    /// 反向代理路由表
+    pub proxy: Vec<ProxyRouteConfig>,
+    /// 响应压缩配置
+    pub compression: CompressionConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            log: LogConfig::default(),
+            storage: StorageConfig::default(),
+            site: SiteConfig::default(),
+            hosts: Vec::new(),
+            proxy: Vec::new(),
+            compression: CompressionConfig::default(),
+        }
+    }
+}
+
+/// 虚拟主机配置
+///
+/// `bucket` 和 `root` 都未设置时使用默认存储（`[storage]`），
+/// 否则使用单独的存储桶或本地目录。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VirtualHostConfig {
+    /// 域名列表，支持精确域名（`example.com`）和通配符（`*.preview.example.com`）
+    pub names: Vec<String>,
+    /// S3 存储桶名称
+    pub bucket: Option<String>,
+    /// 本地目录
+    pub root: Option<PathBuf>,
+    /// 站点配置
+    pub site: SiteConfig,
+}
+
+/// 日志配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: LogLevel,
+    pub format: LogFormat,
+}
+
+/// 响应压缩配置
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// 是否启用响应压缩
+    pub enabled: bool,
+    /// 小于该大小（字节）的响应不压缩
+    pub min_size: u64,
+    /// 可压缩的 MIME 类型白名单，支持 `text/*` 形式的通配
+    pub mime_types: Vec<String>,
+    /// 是否启用 brotli
+    pub br: bool,
+    /// 是否启用 zstd
+    pub zstd: bool,
+    /// 是否启用 gzip
+    pub gzip: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: 1024,
+            mime_types: DEFAULT_COMPRESSIBLE_TYPES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            br: true,
+            zstd: true,
+            gzip: true,
+        }
+    }
+}
+
+/// 反向代理路由配置
+///
+/// 路径前缀匹配的请求（包括所有 HTTP 方法）转发到上游服务，而不是从存储提供文件。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyRouteConfig {
+    /// 路径前缀，如 `/api`，按路径段匹配（`/api` 匹配 `/api/users`，不匹配 `/apis`）
+    pub path: String,
+    /// 上游服务的基础 URL，如 `http://127.0.0.1:8080`
+    pub upstream: String,
+    /// 转发前是否去掉路径前缀（`/api/users` 转发为 `{upstream}/users`）
+    pub strip_prefix: bool,
+    /// 客户端未提供 Authorization 头时注入的服务器 token（以 `Bearer` 方式发送）
+    pub token: Option<String>,
+}
+
+/// 存储配置
+///
+/// `bucket` 和 `root` 必须且只能设置一个
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// S3 存储桶名称
+    pub bucket: Option<String>,
+    /// 本地目录
+    pub root: Option<PathBuf>,
+    /// 存储缓存配置（仅用于 S3）
+    pub cache: StorageCacheConfig,
+}
+
+/// 存储缓存配置，对应 `CacheOptions`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageCacheConfig {
+    /// 对象存在性检查结果的缓存时间（秒）
+    pub exists_ttl: u64,
+    /// 预签名 URL 的缓存时间（秒），实际不超过 URL 有效期的一半
+    pub presign_ttl: u64,
+    /// 每个缓存的最大条目数
+    pub capacity: usize,
+    /// 是否缓存"对象不存在"的结果
+    pub cache_negative: bool,
+    /// 记录缓存命中统计日志的间隔（秒），为 0 时不记录
+    pub stats_interval: u64,
+}
+
+impl Default for StorageCacheConfig {
+    fn default() -> Self {
+        let options = CacheOptions::default();
+        Self {
+            exists_ttl: options.exists_ttl.as_secs(),
+            presign_ttl: options.presign_ttl.as_secs(),
+            capacity: options.capacity,
+            cache_negative: options.cache_negative,
+            stats_interval: 300,
+        }
+    }
+}
+
+impl From<&StorageCacheConfig> for CacheOptions {
+    fn from(config: &StorageCacheConfig) -> Self {
+        Self {
+            exists_ttl: Duration::from_secs(config.exists_ttl),
+            presign_ttl: Duration::from_secs(config.presign_ttl),
+            capacity: config.capacity,
+            cache_negative: config.cache_negative,
+        }
+    }
+}
+
+/// 站点配置
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteConfig {
+    /// 存储中静态文件的前缀，为空时直接使用存储根目录
+    pub prefix: String,
+    /// 索引文件候选列表，按顺序尝试
+    pub index_files: Vec<String>,
+    /// 是否在站点根目录 `/` 返回索引文件，关闭时 `/` 返回 404
+    pub serve_root: bool,
+    /// 请求路径的大小写处理
+    pub path_case: PathCase,
+    /// 客户端接受时优先返回预压缩的 `.br` / `.gz` 同名文件
+    ///
+    /// 默认关闭：开启后可压缩类型的文件请求需要先检查同名文件是否存在（结果有缓存）。
+    pub precompressed: bool,
+    /// SPA 回退策略
+    pub fallback: FallbackConfig,
+    /// 自定义错误页面
+    pub error_pages: ErrorPagesConfig,
+    /// 缓存策略
+    pub cache: CacheConfig,
+    /// 重定向规则，按顺序匹配
+    pub redirect: Vec<RedirectRule>,
+    /// 访问认证规则，按顺序匹配
+    pub auth: Vec<AuthRule>,
+    /// 签名 URL
+    pub signed_urls: SignedUrlConfig,
+    /// 跨域资源共享规则，按顺序匹配，未配置时不允许跨域访问
+    pub cors: Vec<CorsRule>,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "www".to_string(),
+            index_files: vec!["index.html".to_string()],
+            serve_root: true,
+            path_case: PathCase::default(),
+            precompressed: false,
+            fallback: FallbackConfig::default(),
+            error_pages: ErrorPagesConfig::default(),
+            cache: CacheConfig::default(),
+            redirect: Vec::new(),
+            auth: Vec::new(),
+            signed_urls: SignedUrlConfig::default(),
+            cors: Vec::new(),
+        }
+    }
+}
+
+impl SiteConfig {
+    /// 将请求路径映射为存储键，如前缀为 `www` 时 `app.js` 映射为 `www/app.js`
+    pub fn object_key(&self, path: &str) -> String {
+        if self.prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}/{path}", self.prefix)
+        }
+    }
+
+    /// 目录下所有索引文件候选的存储键（按配置顺序），`dir` 为空表示站点根目录
+    pub fn index_keys(&self, dir: &str) -> Vec<String> {
+        self.index_files
+            .iter()
+            .map(|index_file| {
+                if dir.is_empty() {
+                    self.object_key(index_file)
+                } else {
+                    self.object_key(&format!("{dir}/{index_file}"))
+                }
+            })
+            .collect()
+    }
+
+    /// 将存储键映射回请求路径（不含开头的 `/`），是 `object_key` 的逆操作
+    pub fn request_path<'a>(&self, key: &'a str) -> &'a str {
+        if self.prefix.is_empty() {
+            key
+        } else {
+            key.strip_prefix(&self.prefix)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .unwrap_or(key)
+        }
+    }
+
+    /// 查找第一个匹配规范化后的请求路径的认证规则
+    pub fn auth_rule(&self, path: &str) -> Option<&AuthRule> {
+        self.auth
+            .iter()
+            .find(|rule| rule.is_match(path, self.path_case))
+    }
+
+    /// 查找第一个匹配规范化后的请求路径的 CORS 规则
+    pub fn cors_rule(&self, path: &str) -> Option<&CorsRule> {
+        self.cors
+            .iter()
+            .find(|rule| path_prefix_matches(&rule.path, path, self.path_case))
+    }
+
+    /// 查找第一个匹配请求路径（不含开头的 `/`）的重定向规则
+    pub fn redirect_rule(&self, path: &str) -> Option<&RedirectRule> {
+        self.redirect
+            .iter()
+            .find(|rule| rule.pattern.is_match(path))
+    }
+}
+
+/// 默认不做 SPA 回退的静态资源扩展名
+pub const DEFAULT_ASSET_EXTENSIONS: &[&str] = &[
+    "js", "mjs", "cjs", "css", "map", "json", "wasm", "png", "jpg", "jpeg", "gif", "webp", "avif",
+    "svg", "ico", "woff", "woff2", "ttf", "otf", "eot",
+];
+
+/// SPA 回退策略
+///
+/// 请求的文件不存在时，只有看起来像页面导航的请求（`Accept` 包含 `text/html`，
+/// 或路径的最后一段没有扩展名）才回退到上级目录的索引文件；静态资源扩展名从不回退，
+/// 避免缺失的 `/assets/app.js` 以 HTML 返回导致浏览器报 MIME 类型错误。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FallbackConfig {
+    /// 是否启用 SPA 回退，关闭时不存在的文件直接返回 404（目录索引不受影响）
+    pub enabled: bool,
+    /// 回退响应的状态码，200 或 404
+    pub status: u16,
+    /// 从不回退的扩展名（不区分大小写）
+    pub asset_extensions: Vec<String>,
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            status: 200,
+            asset_extensions: DEFAULT_ASSET_EXTENSIONS
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl FallbackConfig {
+    /// 判断请求是否应该回退到索引文件
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 请求路径（不含开头的 `/`）
+    /// * `accept` - 请求的 Accept 头值
+    pub fn applies(&self, path: &str, accept: Option<&str>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let filename = path.rsplit('/').next().unwrap_or(path);
+        let ext = filename.rsplit_once('.').map(|(_, ext)| ext);
+        if let Some(ext) = ext
+            && self
+                .asset_extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(ext))
+        {
+            return false;
+        }
+        let accepts_html = accept.is_some_and(|v| v.to_ascii_lowercase().contains("text/html"));
+        ext.is_none() || accepts_html
+    }
+
+    /// 回退响应的状态码
+    pub fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !matches!(self.status, 200 | 404) {
+            return Err(ConfigError::Invalid(format!(
+                "fallback status must be 200 or 404, got {}",
+                self.status
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 自定义错误页面配置
+///
+/// 页面路径相对于站点前缀，找不到或读取失败时返回内置的最小错误页面。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ErrorPagesConfig {
+    /// 是否从存储中查找自定义错误页面，关闭时总是使用内置页面
+    pub enabled: bool,
+    /// 404 页面的文件名，从请求路径所在目录开始逐级向上查找
+    pub not_found: String,
+    /// 5xx 页面的路径（相对于站点根目录）
+    pub server_error: String,
+}
+
+impl Default for ErrorPagesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            not_found: "404.html".to_string(),
+            server_error: "50x.html".to_string(),
+        }
+    }
+}
+
+impl ErrorPagesConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid_segment = |s: &str| s.is_empty() || s == "." || s == "..";
+        if invalid_segment(&self.not_found) || self.not_found.contains('/') {
+            return Err(ConfigError::Invalid(format!(
+                "invalid error_pages.not_found '{}'",
+                self.not_found
+            )));
+        }
+        if self.server_error.split('/').any(invalid_segment) {
+            return Err(ConfigError::Invalid(format!(
+                "invalid error_pages.server_error '{}'",
+                self.server_error
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 缓存策略配置
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// 未带内容哈希的可缓存文件的 max-age（秒），过期后需要重新验证
+    pub max_age: u64,
+    /// 不缓存的文件扩展名（不区分大小写），返回 `no-cache`
+    pub no_cache_extensions: Vec<String>,
+    /// 对象自身带有 Cache-Control 元数据时直接使用，优先于缓存规则
+    pub trust_object_cache_control: bool,
+    /// 缓存规则，按顺序匹配，优先于按扩展名的默认策略
+    pub rules: Vec<CacheRule>,
+    /// 文件名带内容哈希的资源使用 `immutable` 长期缓存
+    pub immutable_hashed: bool,
+    /// 内容哈希文件名的匹配模式（正则表达式，匹配文件名）
+    pub hashed_pattern: PathRegex,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_age: CACHE_MAX_AGE,
+            no_cache_extensions: NO_CACHE_EXTS.iter().map(|e| e.to_string()).collect(),
+            trust_object_cache_control: false,
+            rules: Vec::new(),
+            immutable_hashed: true,
+            hashed_pattern: PathRegex::new(DEFAULT_HASHED_FILENAME_PATTERN)
+                .expect("default hashed filename pattern is valid"),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// 可缓存文件使用的 Cache-Control 头值
+    pub fn cache_control_value(&self) -> String {
+        format!("public, max-age={}, must-revalidate", self.max_age)
+    }
+
+    /// 判断存储键的文件名是否带内容哈希（未开启 `immutable_hashed` 时总是返回 false）
+    pub fn is_hashed(&self, key: &str) -> bool {
+        let filename = key.rsplit('/').next().unwrap_or(key);
+        self.immutable_hashed && self.hashed_pattern.is_match(filename)
+    }
+
+    /// 查找第一个匹配请求路径（不含开头的 `/`）的缓存规则
+    pub fn rule(&self, path: &str) -> Option<&CacheRule> {
+        self.rules.iter().find(|rule| rule.is_match(path))
+    }
+}
+
+/// 缓存规则：请求路径匹配 `glob` 或 `regex`（二选一）时使用指定的 Cache-Control
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheRule {
+    /// 请求路径匹配模式（glob）
+    #[serde(default)]
+    pub glob: Option<PathPattern>,
+    /// 请求路径匹配模式（正则表达式，未加锚点时匹配路径的任意部分）
+    #[serde(default)]
+    pub regex: Option<PathRegex>,
+    /// Cache-Control 头值
+    pub cache_control: String,
+}
+
+impl CacheRule {
+    pub fn is_match(&self, path: &str) -> bool {
+        self.glob.as_ref().is_some_and(|p| p.is_match(path))
+            || self.regex.as_ref().is_some_and(|r| r.is_match(path))
+    }
+
+    /// 规则的匹配模式，用于错误信息
+    fn pattern(&self) -> &str {
+        self.glob
+            .as_ref()
+            .map(PathPattern::as_str)
+            .or_else(|| self.regex.as_ref().map(PathRegex::as_str))
+            .unwrap_or_default()
+    }
+
+    /// 校验匹配模式（glob 和 regex 必须且只能设置一个）和 Cache-Control 头值
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.glob.is_some() == self.regex.is_some() {
+            return Err(ConfigError::Invalid(
+                "cache rule: exactly one of glob and regex must be set".to_string(),
+            ));
+        }
+        if self.cache_control.trim().is_empty()
+            || HeaderValue::from_str(&self.cache_control).is_err()
+        {
+            return Err(ConfigError::Invalid(format!(
+                "cache rule '{}': invalid cache_control '{}'",
+                self.pattern(),
+                self.cache_control
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 路径匹配模式（正则表达式）
+#[derive(Debug, Clone)]
+pub struct PathRegex(regex::Regex);
+
+impl PathRegex {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        regex::Regex::new(pattern).map(Self)
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.0.is_match(path)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for PathRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for PathRegex {}
+
+impl<'de> Deserialize<'de> for PathRegex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 路径匹配模式（glob）
+///
+/// 匹配不含开头 `/` 的请求路径：`*` 不跨越目录，`**` 匹配任意层级，
+/// 如 `downloads/**`、`**/*.zip`。
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pattern: String,
+    matcher: globset::GlobMatcher,
+}
+
+impl PathPattern {
+    pub fn new(pattern: &str) -> Result<Self, globset::Error> {
+        let matcher = globset::GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()?
+            .compile_matcher();
+        Ok(Self {
+            pattern: pattern.to_string(),
+            matcher,
+        })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.matcher.is_match(path)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl PartialEq for PathPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for PathPattern {}
+
+impl<'de> Deserialize<'de> for PathPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 重定向规则
+///
+/// 匹配的请求不再由本服务转发文件内容，而是重定向到对象的预签名 URL，
+/// 适用于大文件下载。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedirectRule {
+    /// 请求路径匹配模式
+    pub pattern: PathPattern,
+    /// 重定向状态码，302 或 307
+    #[serde(default = "RedirectRule::default_status")]
+    pub status: u16,
+    /// 预签名 URL 的有效期（秒），不能超过 7 天
+    #[serde(default = "RedirectRule::default_expires_in")]
+    pub expires_in: u64,
+    /// 通过 `response-content-disposition` 覆盖下载时的 Content-Disposition
+    #[serde(default)]
+    pub disposition: Option<Disposition>,
+}
+
+impl RedirectRule {
+    fn default_status() -> u16 {
+        302
+    }
+
+    fn default_expires_in() -> u64 {
+        300
+    }
+
+    /// 校验状态码和有效期
+    fn validate(&self) -> Result<(), ConfigError> {
+        let pattern = self.pattern.as_str();
+        if !matches!(self.status, 302 | 307) {
+            return Err(ConfigError::Invalid(format!(
+                "redirect '{pattern}': status must be 302 or 307, got {}",
+                self.status
+            )));
+        }
+        if self.expires_in == 0 || self.expires_in > MAX_PRESIGNED_URL_EXPIRY.as_secs() {
+            return Err(ConfigError::Invalid(format!(
+                "redirect '{pattern}': expires_in must be between 1 and {} seconds",
+                MAX_PRESIGNED_URL_EXPIRY.as_secs()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 访问认证规则
+///
+/// 规范化后的请求路径以 `path` 为前缀（按路径段匹配）时需要认证，第一个匹配的规则生效。
+/// 同一规则可以同时启用多种认证方式，任意一种通过即可访问。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthRule {
+    /// 路径前缀，如 `/internal`，`/` 表示整个站点
+    pub path: String,
+    /// 认证域，出现在 `WWW-Authenticate` 质询中
+    #[serde(default = "AuthRule::default_realm")]
+    pub realm: String,
+    /// Basic 认证使用的 htpasswd 文件（加载配置时读取）
+    #[serde(default)]
+    pub htpasswd: Option<Htpasswd>,
+    /// 允许的静态 bearer token
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// JWT bearer token 校验
+    #[serde(default)]
+    pub jwt: Option<JwtConfig>,
+}
+
+impl AuthRule {
+    fn default_realm() -> String {
+        "Restricted".to_string()
+    }
+
+    /// 是否接受 bearer token（静态 token 或 JWT）
+    pub fn accepts_bearer(&self) -> bool {
+        !self.tokens.is_empty() || self.jwt.is_some()
+    }
+
+    /// 判断规范化后的请求路径（不含开头的 `/`）是否位于路径前缀之下
+    ///
+    /// 请求路径转为小写时，前缀也按小写比较。
+    pub fn is_match(&self, path: &str, case: PathCase) -> bool {
+        path_prefix_matches(&self.path, path, case)
+    }
+
+    /// 校验认证规则，并规范化路径前缀（去掉末尾的斜杠）
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        self.path = normalize_path_prefix(&self.path)
+            .ok_or_else(|| ConfigError::Invalid(format!("invalid auth path '{}'", self.path)))?;
+        let path = &self.path;
+
+        if self.realm.is_empty()
+            || self.realm.contains(['"', '\\'])
+            || HeaderValue::from_str(&self.realm).is_err()
+        {
+            return invalid(format!("auth '{path}': invalid realm '{}'", self.realm));
+        }
+        if self.htpasswd.is_none() && !self.accepts_bearer() {
+            return invalid(format!(
+                "auth '{path}': at least one of htpasswd, tokens or jwt must be configured"
+            ));
+        }
+        if self.tokens.iter().any(|token| {
+            token.is_empty()
+                || token.contains(char::is_whitespace)
+                || HeaderValue::from_str(token).is_err()
+        }) {
+            return invalid(format!("auth '{path}': invalid token"));
+        }
+        if let Some(jwt) = &self.jwt {
+            if jwt.secret.is_none() && jwt.jwks.is_none() {
+                return invalid(format!(
+                    "auth '{path}': jwt requires a secret (HS256) or a jwks file (RS256)"
+                ));
+            }
+            if jwt.secret.as_ref().is_some_and(String::is_empty) {
+                return invalid(format!("auth '{path}': jwt secret must not be empty"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 规范化路径前缀：以 `/` 开头、不以 `/` 结尾（根目录为 `/`），包含空段、`.` 或 `..` 时返回 `None`
+fn normalize_path_prefix(prefix: &str) -> Option<String> {
+    let prefix = format!("/{}", prefix.trim_matches('/'));
+    let valid = prefix == "/"
+        || !prefix[1..]
+            .split('/')
+            .any(|s| s.is_empty() || s == "." || s == "..");
+    valid.then_some(prefix)
+}
+
+/// 判断规范化后的请求路径（不含开头的 `/`）是否位于路径前缀之下（按路径段匹配）
+///
+/// 请求路径转为小写时，前缀也按小写比较。
+fn path_prefix_matches(prefix: &str, path: &str, case: PathCase) -> bool {
+    let prefix = prefix.trim_start_matches('/');
+    let prefix = match case {
+        PathCase::Preserve => Cow::Borrowed(prefix),
+        PathCase::Lowercase => Cow::Owned(prefix.to_lowercase()),
+    };
+    prefix.is_empty()
+        || path
+            .strip_prefix(prefix.as_ref())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// 签名 URL 配置
+///
+/// 路径前缀下的请求需要带有效的签名和过期时间（见 `signed_url` 模块），
+/// 用于分发有时效的私有文件链接而不暴露存储桶。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignedUrlConfig {
+    /// 需要签名才能访问的路径前缀，如 `/private`
+    pub paths: Vec<String>,
+    /// 签名密钥，第一个用于生成新的签名，全部用于校验（轮换时把新密钥放在最前面）
+    pub keys: Vec<SigningKey>,
+    /// 校验过期时间时允许的时钟偏差（秒）
+    pub clock_skew: u64,
+}
+
+impl Default for SignedUrlConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            keys: Vec::new(),
+            clock_skew: 30,
+        }
+    }
+}
+
+/// 签名密钥
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigningKey {
+    /// 密钥 ID，出现在签名 URL 的 `kid` 参数中
+    pub id: String,
+    /// HMAC-SHA256 密钥，至少 16 字节
+    pub secret: String,
+}
+
+impl SignedUrlConfig {
+    /// 判断规范化后的请求路径是否需要签名
+    pub fn is_protected(&self, path: &str, case: PathCase) -> bool {
+        self.paths
+            .iter()
+            .any(|prefix| path_prefix_matches(prefix, path, case))
+    }
+
+    /// 按 ID 查找密钥
+    pub fn key(&self, id: &str) -> Option<&SigningKey> {
+        self.keys.iter().find(|key| key.id == id)
+    }
+
+    /// 校验签名 URL 配置，并规范化路径前缀
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        for prefix in &mut self.paths {
+            *prefix = normalize_path_prefix(prefix).ok_or_else(|| {
+                ConfigError::Invalid(format!("invalid signed URL path '{prefix}'"))
+            })?;
+        }
+        if !self.paths.is_empty() && self.keys.is_empty() {
+            return invalid("signed_urls: at least one key must be configured".to_string());
+        }
+        let mut ids = std::collections::HashSet::new();
+        for key in &self.keys {
+            if key.id.is_empty()
+                || !key
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                return invalid(format!("signed_urls: invalid key id '{}'", key.id));
+            }
+            if !ids.insert(key.id.as_str()) {
+                return invalid(format!(
+                    "signed_urls: key '{}' is configured more than once",
+                    key.id
+                ));
+            }
+            if key.secret.len() < 16 {
+                return invalid(format!(
+                    "signed_urls: secret of key '{}' must be at least 16 bytes",
+                    key.id
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 跨域资源共享（CORS）规则
+///
+/// 规范化后的请求路径以 `path` 为前缀（按路径段匹配）时生效，第一个匹配的规则生效。
+/// 来源为 `*`（任意来源）、完整的来源（如 `https://app.example.com`）或通配子域名
+/// （如 `https://*.example.com`，匹配任意层级的子域名，但不匹配 `example.com` 本身）。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsRule {
+    /// 路径前缀，如 `/api`，`/` 表示整个站点
+    pub path: String,
+    /// 允许的来源
+    pub origins: Vec<String>,
+    /// 允许的请求方法
+    #[serde(default = "CorsRule::default_methods")]
+    pub methods: Vec<String>,
+    /// 预检请求中允许的请求头，`*` 表示允许任意请求头
+    #[serde(default)]
+    pub headers: Vec<String>,
+    /// 允许浏览器脚本读取的响应头
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    /// 是否允许携带 Cookie 等凭证，不能与来源 `*` 同时使用
+    #[serde(default)]
+    pub credentials: bool,
+    /// 浏览器缓存预检结果的时间（秒），未设置时由浏览器决定
+    #[serde(default)]
+    pub max_age: Option<u64>,
+}
+
+impl CorsRule {
+    fn default_methods() -> Vec<String> {
+        vec!["GET".to_string(), "HEAD".to_string()]
+    }
+
+    /// 是否允许任意来源
+    pub fn allows_any_origin(&self) -> bool {
+        self.origins.iter().any(|origin| origin == "*")
+    }
+
+    /// 判断请求的 `Origin` 是否被允许，比较不区分大小写
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.origins.iter().any(|allowed| {
+            if allowed == "*" {
+                return true;
+            }
+            let Some((scheme, suffix)) = allowed.split_once("://*") else {
+                return *allowed == origin;
+            };
+            origin
+                .strip_prefix(scheme)
+                .and_then(|rest| rest.strip_prefix("://"))
+                .and_then(|rest| rest.strip_suffix(suffix))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && !subdomain.starts_with('.')
+                        && subdomain
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                })
+        })
+    }
+
+    /// 判断请求方法是否被允许（区分大小写）
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.methods.iter().any(|allowed| allowed == method)
+    }
+
+    /// 判断请求头是否被允许，比较不区分大小写
+    pub fn allows_header(&self, name: &str) -> bool {
+        self.headers
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(name))
+    }
+
+    /// 校验 CORS 规则，并规范化路径前缀、来源、方法和头部名称
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        self.path = normalize_path_prefix(&self.path)
+            .ok_or_else(|| ConfigError::Invalid(format!("invalid cors path '{}'", self.path)))?;
+        let path = &self.path;
+
+        if self.origins.is_empty() {
+            return invalid(format!(
+                "cors '{path}': at least one origin must be configured"
+            ));
+        }
+        for origin in &mut self.origins {
+            *origin = normalize_origin(origin).ok_or_else(|| {
+                ConfigError::Invalid(format!("cors '{path}': invalid origin '{origin}'"))
+            })?;
+        }
+        if self.credentials && self.allows_any_origin() {
+            return invalid(format!(
+                "cors '{path}': credentials cannot be combined with origin '*'"
+            ));
+        }
+
+        if self.methods.is_empty() {
+            return invalid(format!(
+                "cors '{path}': at least one method must be configured"
+            ));
+        }
+        for method in &mut self.methods {
+            *method = method.trim().to_ascii_uppercase();
+            if axum::http::Method::from_bytes(method.as_bytes()).is_err() {
+                return invalid(format!("cors '{path}': invalid method '{method}'"));
+            }
+        }
+
+        for name in self.headers.iter_mut().chain(&mut self.expose_headers) {
+            *name = name.trim().to_ascii_lowercase();
+            if name != "*" && axum::http::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return invalid(format!("cors '{path}': invalid header name '{name}'"));
+            }
+        }
+        if self.credentials && self.expose_headers.iter().any(|name| name == "*") {
+            return invalid(format!(
+                "cors '{path}': credentials cannot be combined with expose_headers '*'"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// 规范化 CORS 来源为 `scheme://host[:port]` 形式（小写，去掉默认端口和末尾的斜杠）
+///
+/// 只接受 `*` 和 http / https 来源，带路径、查询参数或用户信息时返回 `None`。
+fn normalize_origin(origin: &str) -> Option<String> {
+    let origin = origin.trim();
+    if origin == "*" {
+        return Some(origin.to_string());
+    }
+    // 通配子域名替换为占位标签后按普通来源解析
+    let (origin, wildcard) = match origin.split_once("://*.") {
+        Some((scheme, rest)) => (format!("{scheme}://x.{rest}"), true),
+        None => (origin.to_string(), false),
+    };
+    let url = reqwest::Url::parse(&origin).ok()?;
+    if !matches!(url.scheme(), "http" | "https")
+        || url.host_str().is_none()
+        || url.path() != "/"
+        || url.query().is_some()
+        || url.fragment().is_some()
+        || !url.username().is_empty()
+        || url.password().is_some()
+    {
+        return None;
+    }
+    let origin = url.origin().ascii_serialization();
+    Some(if wildcard {
+        origin.replacen("://x.", "://*.", 1)
+    } else {
+        origin
+    })
+}
+
+/// JWT 校验配置
+///
+/// HS256 使用共享密钥，RS256 使用本地 JWKS 文件中的公钥（按 JWT 头中的 `kid` 选择）。
+/// 总是校验 `exp`，配置了 `issuer` / `audience` 时同时校验 `iss` / `aud`。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtConfig {
+    /// HS256 共享密钥
+    pub secret: Option<String>,
+    /// RS256 公钥所在的 JWKS 文件（加载配置时读取）
+    pub jwks: Option<Jwks>,
+    /// 要求的签发者（`iss`）
+    pub issuer: Option<String>,
+    /// 要求的受众（`aud`）
+    pub audience: Option<String>,
+    /// 校验 `exp` / `nbf` 时允许的时钟偏差（秒）
+    pub leeway: u64,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            jwks: None,
+            issuer: None,
+            audience: None,
+            leeway: 60,
+        }
+    }
+}
+
+/// 请求路径的大小写处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathCase {
+    /// 保留请求路径的大小写
+    #[default]
+    Preserve,
+    /// 转为小写后再查找对象（适用于以小写文件名上传、需要大小写不敏感访问的站点）
+    Lowercase,
+}
+
+/// Content-Disposition 类型，文件名取自对象键的最后一段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Disposition {
+    /// 在浏览器中直接打开
+    Inline,
+    /// 作为附件下载
+    Attachment,
+}
+
+impl Config {
+    /// 加载配置：读取配置文件（如果指定），应用命令行参数和环境变量，然后校验
+    ///
+    /// 存储配置只在启动服务时校验，`sign` 等子命令不访问存储。
+    pub fn load(cli: &Cli) -> Result<Self, ConfigError> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_cli(cli);
+        if cli.command.is_none() {
+            config.validate_storage()?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 按主机名（可带端口）查找站点配置，使用与虚拟主机表相同的 `HostMatcher`，
+    /// 未匹配时返回顶层站点配置
+    pub fn site_for_host(&self, host: &str) -> &SiteConfig {
+        let mut matcher = HostMatcher::new();
+        for host_config in &self.hosts {
+            for name in &host_config.names {
+                matcher.add(name, &host_config.site);
+            }
+        }
+        matcher.get(host).copied().unwrap_or(&self.site)
+    }
+
+    /// 从 TOML 配置文件读取配置（不校验）
+    pub fn from_file(path: &std::path::Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// 用命令行参数（及其环境变量）覆盖配置
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(listen) = cli.listen {
+            self.listen = listen;
+        }
+        if let Some(level) = cli.log_level {
+            self.log.level = level;
+        }
+        if let Some(format) = cli.log_format {
+            self.log.format = format;
+        }
+        // 命令行指定了一种存储时，覆盖配置文件中的另一种
+        if let Some(bucket) = &cli.bucket {
+            self.storage.bucket = Some(bucket.clone());
+            self.storage.root = None;
+        }
+        if let Some(root) = &cli.root {
+            self.storage.root = Some(root.clone());
+            if cli.bucket.is_none() {
+                self.storage.bucket = None;
+            }
+        }
+        if let Some(prefix) = &cli.prefix {
+            self.site.prefix = prefix.clone();
+        }
+        if let Some(index_files) = &cli.index_files {
+            self.site.index_files = index_files.clone();
+        }
+        if let Some(serve_root) = cli.serve_root {
+            self.site.serve_root = serve_root;
+        }
+        if let Some(enabled) = cli.spa_fallback {
+            self.site.fallback.enabled = enabled;
+        }
+        if let Some(max_age) = cli.cache_max_age {
+            self.site.cache.max_age = max_age;
+        }
+        if let Some(exts) = &cli.no_cache_extensions {
+            self.site.cache.no_cache_extensions = exts.clone();
+        }
+    }
+
+    /// 校验存储配置：必须且只能设置存储桶和本地目录之一
+    pub fn validate_storage(&self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        match (&self.storage.bucket, &self.storage.root) {
+            (None, None) => invalid(
+                "either a bucket (--bucket / AWS_BUCKET) or a local root (--root / STATIC_ROOT) must be set"
+                    .to_string(),
+            ),
+            (Some(_), Some(_)) => invalid("bucket and root are mutually exclusive".to_string()),
+            (Some(bucket), None) if bucket.trim().is_empty() => {
+                invalid("bucket must not be empty".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 校验存储以外的配置，并规范化前缀（去掉首尾斜杠，允许为空）和虚拟主机域名
+    pub fn validate(&mut self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        self.site.validate()?;
+
+        let mut paths = std::collections::HashSet::new();
+        for route in &mut self.proxy {
+            route.validate()?;
+            if !paths.insert(route.path.clone()) {
+                return invalid(format!(
+                    "proxy path '{}' is configured more than once",
+                    route.path
+                ));
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for host in &mut self.hosts {
+            host.validate()?;
+            if let Some(name) = host
+                .names
+                .iter()
+                .find(|name| !seen.insert(name.to_string()))
+            {
+                return invalid(format!("host '{name}' is configured more than once"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SiteConfig {
+    /// 校验站点配置，并规范化前缀（去掉首尾斜杠，允许为空）
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        self.prefix = self.prefix.trim_matches('/').to_string();
+        if !self.prefix.is_empty()
+            && self
+                .prefix
+                .split('/')
+                .any(|s| s.is_empty() || s == "." || s == "..")
+        {
+            return invalid(format!("invalid prefix '{}'", self.prefix));
+        }
+        if self.index_files.is_empty() {
+            return invalid("at least one index file must be configured".to_string());
+        }
+        if let Some(index_file) = self
+            .index_files
+            .iter()
+            .find(|f| f.is_empty() || f.contains('/') || *f == "." || *f == "..")
+        {
+            return invalid(format!("invalid index file '{index_file}'"));
+        }
+        for rule in &self.redirect {
+            rule.validate()?;
+        }
+        for rule in &self.cache.rules {
+            rule.validate()?;
+        }
+        self.fallback.validate()?;
+        self.error_pages.validate()?;
+        for rule in &mut self.auth {
+            rule.validate()?;
+        }
+        self.signed_urls.validate()?;
+        for rule in &mut self.cors {
+            rule.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProxyRouteConfig {
+    /// 校验代理路由配置，并规范化路径前缀（去掉末尾的斜杠）
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        self.path = format!("/{}", self.path.trim_matches('/'));
+        if self.path == "/"
+            || self.path[1..]
+                .split('/')
+                .any(|s| s.is_empty() || s == "." || s == "..")
+        {
+            return invalid(format!("invalid proxy path '{}'", self.path));
+        }
+
+        match reqwest::Url::parse(&self.upstream) {
+            Ok(url)
+                if matches!(url.scheme(), "http" | "https")
+                    && url.query().is_none()
+                    && url.fragment().is_none() => {}
+            _ => {
+                return invalid(format!(
+                    "proxy '{}': invalid upstream URL '{}'",
+                    self.path, self.upstream
+                ));
+            }
+        }
+
+        if let Some(token) = &self.token
+            && (token.is_empty() || HeaderValue::from_str(&format!("Bearer {token}")).is_err())
+        {
+            return invalid(format!("proxy '{}': invalid token", self.path));
+        }
+
+        Ok(())
+    }
+}
+
+impl VirtualHostConfig {
+    /// 校验虚拟主机配置，并将域名统一转换为小写
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        if self.names.is_empty() {
+            return invalid("virtual host must have at least one name".to_string());
+        }
+        for name in &mut self.names {
+            *name = name.trim().trim_end_matches('.').to_ascii_lowercase();
+            let host = name.strip_prefix("*.").unwrap_or(name);
+            if host.is_empty()
+                || host.split('.').any(str::is_empty)
+                || !host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+            {
+                return invalid(format!("invalid host name '{name}'"));
+            }
+        }
+
+        match (&self.bucket, &self.root) {
+            (Some(_), Some(_)) => {
+                return invalid(format!(
+                    "host '{}': bucket and root are mutually exclusive",
+                    self.names[0]
+                ));
+            }
+            (Some(bucket), None) if bucket.trim().is_empty() => {
+                return invalid(format!(
+                    "host '{}': bucket must not be empty",
+                    self.names[0]
+                ));
+            }
+            _ => {}
+        }
+
+        self.site.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    /// 测试配置文件解析
+    ///
+    /// 验证：
+    /// - 各配置项正确读取
+    /// - 未设置的配置项使用默认值
+    #[test]
+    fn test_parse_config_file() {
+        let config = parse(
+            r#"
+            listen = "127.0.0.1:8080"
+
+            [log]
+            level = "info"
+            format = "json"
+
+            [storage]
+            bucket = "my-bucket"
+
+            [site]
+            index_files = ["index.htm", "index.html"]
+            precompressed = true
+
+            [site.cache]
+            max_age = 600
+
+            [compression]
+            min_size = 2048
+            br = false
+            "#,
+        );
+
+        assert_eq!(config.listen, "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(config.log.level, LogLevel::Info);
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert_eq!(config.storage.bucket.as_deref(), Some("my-bucket"));
+        assert_eq!(config.site.prefix, "www");
+        assert_eq!(config.site.index_files, ["index.htm", "index.html"]);
+        assert!(config.site.precompressed);
+        assert_eq!(config.site.cache.max_age, 600);
+        assert_eq!(config.site.cache.no_cache_extensions, ["html", "htm"]);
+        assert_eq!(config.storage.cache, StorageCacheConfig::default());
+        assert_eq!(config.compression.min_size, 2048);
+        assert!(!config.compression.br);
+        assert!(config.compression.enabled && config.compression.gzip);
+    }
+
+    /// 测试未知配置项被拒绝
+    ///
+    /// 验证：
+    /// - 拼写错误的配置项会导致解析失败而不是被静默忽略
+    /// - 枚举配置项只接受已知的取值
+    #[test]
+    fn test_unknown_field_rejected() {
+        assert!(toml::from_str::<Config>("listne = \"0.0.0.0:80\"").is_err());
+        assert!(toml::from_str::<Config>("[site]\nindex = \"a.html\"").is_err());
+        assert!(toml::from_str::<Config>("[site]\npath_case = \"upper\"").is_err());
+        let config: Config = toml::from_str("[site]\npath_case = \"lowercase\"").unwrap();
+        assert_eq!(config.site.path_case, PathCase::Lowercase);
+    }
+
+    /// 测试命令行参数覆盖配置文件
+    ///
+    /// 验证：
+    /// - 命令行参数优先于配置文件
+    /// - 命令行指定本地目录时覆盖配置文件中的存储桶
+    #[test]
+    fn test_cli_overrides_file() {
+        let mut config = parse(
+            r#"
+            listen = "127.0.0.1:8080"
+            [storage]
+            bucket = "from-file"
+            [site]
+            prefix = "www"
+            "#,
+        );
+        let cli = Cli::try_parse_from([
+            "static-server",
+            "--listen",
+            "0.0.0.0:9000",
+            "--root",
+            "./dist",
+            "--prefix",
+            "/sites/marketing/",
+            "--no-cache-extensions",
+            "html,json",
+            "--index-files",
+            "index.htm,index.html",
+            "--serve-root",
+            "false",
+            "--spa-fallback",
+            "false",
+        ])
+        .unwrap();
+
+        config.apply_cli(&cli);
+        config.validate().unwrap();
+
+        assert_eq!(config.listen, "0.0.0.0:9000".parse().unwrap());
+        assert_eq!(config.storage.bucket, None);
+        assert_eq!(config.storage.root, Some(PathBuf::from("./dist")));
+        assert_eq!(config.site.prefix, "sites/marketing");
+        assert_eq!(config.site.cache.no_cache_extensions, ["html", "json"]);
+        assert_eq!(config.site.index_files, ["index.htm", "index.html"]);
+        assert!(!config.site.serve_root);
+        assert!(!config.site.fallback.enabled);
+    }
+
+    /// 测试存储键的拼接
+    ///
+    /// 验证：
+    /// - 有前缀时加上 `{prefix}/`，前缀为空时直接使用请求路径
+    /// - 索引文件候选按配置顺序生成，根目录不带多余的斜杠
+    #[test]
+    fn test_site_keys() {
+        let mut site = SiteConfig {
+            prefix: "sites/marketing".to_string(),
+            index_files: vec!["index.html".to_string(), "index.htm".to_string()],
+            ..SiteConfig::default()
+        };
+        assert_eq!(site.object_key("app.js"), "sites/marketing/app.js");
+        assert_eq!(
+            site.index_keys("docs"),
+            [
+                "sites/marketing/docs/index.html",
+                "sites/marketing/docs/index.htm"
+            ]
+        );
+        assert_eq!(
+            site.index_keys(""),
+            ["sites/marketing/index.html", "sites/marketing/index.htm"]
+        );
+
+        site.prefix.clear();
+        assert_eq!(site.object_key("app.js"), "app.js");
+        assert_eq!(site.index_keys(""), ["index.html", "index.htm"]);
+    }
+
+    /// 测试配置校验
+    ///
+    /// 验证：
+    /// - 未设置存储、同时设置两种存储时存储校验报错
+    /// - 前缀可以为空，无效的前缀报错
+    /// - 索引文件列表为空或包含无效文件名时报错
+    #[test]
+    fn test_validate() {
+        let valid = || Config {
+            storage: StorageConfig {
+                bucket: Some("bucket".to_string()),
+                ..StorageConfig::default()
+            },
+            ..Config::default()
+        };
+        assert!(valid().validate_storage().is_ok());
+        assert!(valid().validate().is_ok());
+
+        assert!(matches!(
+            Config::default().validate_storage(),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        let mut config = valid();
+        config.storage.root = Some(PathBuf::from("./dist"));
+        assert!(config.validate_storage().is_err());
+
+        for prefix in ["", "/", "sites/marketing"] {
+            let mut config = valid();
+            config.site.prefix = prefix.to_string();
+            assert!(config.validate().is_ok(), "{prefix}");
+        }
+        for prefix in ["a/../b", "a//b", "./a"] {
+            let mut config = valid();
+            config.site.prefix = prefix.to_string();
+            assert!(config.validate().is_err(), "{prefix}");
+        }
+
+        let mut config = valid();
+        config.site.index_files = vec!["index.html".to_string(), "a/index.html".to_string()];
+        assert!(config.validate().is_err());
+
+        let mut config = valid();
+        config.site.index_files.clear();
+        assert!(config.validate().is_err());
+    }
+
+    /// 测试重定向规则配置
+    ///
+    /// 验证：
+    /// - `[[site.redirect]]` 正确解析，未设置的字段使用默认值
+    /// - glob 模式中 `*` 不跨越目录，`**` 匹配任意层级
+    /// - 无效的 glob 模式在解析时报错，无效的状态码和有效期在校验时报错
+    #[test]
+    fn test_redirect_rules() {
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+
+            [[site.redirect]]
+            pattern = "downloads/**"
+            status = 307
+            expires_in = 60
+            disposition = "attachment"
+
+            [[site.redirect]]
+            pattern = "*.iso"
+            "#,
+        );
+        config.validate().unwrap();
+
+        let rules = &config.site.redirect;
+        assert_eq!(rules[0].status, 307);
+        assert_eq!(rules[0].expires_in, 60);
+        assert_eq!(rules[0].disposition, Some(Disposition::Attachment));
+        assert_eq!(rules[1].status, 302);
+        assert_eq!(rules[1].expires_in, 300);
+        assert_eq!(rules[1].disposition, None);
+
+        let rule_of = |path| config.site.redirect_rule(path).map(|r| r.pattern.as_str());
+        assert_eq!(rule_of("downloads/a/b.zip"), Some("downloads/**"));
+        assert_eq!(rule_of("ubuntu.iso"), Some("*.iso"));
+        assert_eq!(rule_of("images/ubuntu.iso"), None);
+        assert_eq!(rule_of("app.js"), None);
+
+        assert!(toml::from_str::<Config>("[[site.redirect]]\npattern = \"a/[\"").is_err());
+
+        for rule in [
+            "pattern = \"a/**\"\nstatus = 301",
+            "pattern = \"a/**\"\nexpires_in = 0",
+            "pattern = \"a/**\"\nexpires_in = 604801",
+        ] {
+            let mut config = parse(&format!(
+                "[storage]\nbucket = \"main\"\n[[site.redirect]]\n{rule}"
+            ));
+            assert!(config.validate().is_err(), "{rule}");
+        }
+    }
+
+    /// 测试缓存规则配置
+    ///
+    /// 验证：
+    /// - `[[site.cache.rules]]` 的 glob 和 regex 规则正确解析，按顺序匹配
+    /// - `request_path` 将存储键还原为请求路径
+    /// - glob 和 regex 同时设置或都未设置、无效的 Cache-Control 在校验时报错，无效的正则在解析时报错
+    #[test]
+    fn test_cache_rules() {
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+
+            [site.cache]
+            trust_object_cache_control = true
+
+            [[site.cache.rules]]
+            glob = "assets/**/*-????????.js"
+            cache_control = "public, max-age=31536000, immutable"
+
+            [[site.cache.rules]]
+            regex = '^service-worker\.js$'
+            cache_control = "no-cache"
+            "#,
+        );
+        config.validate().unwrap();
+
+        let cache = &config.site.cache;
+        assert!(cache.trust_object_cache_control);
+        let rule_of = |path| cache.rule(path).map(|r| r.cache_control.as_str());
+        assert_eq!(
+            rule_of("assets/js/app-0123abcd.js"),
+            Some("public, max-age=31536000, immutable")
+        );
+        assert_eq!(rule_of("service-worker.js"), Some("no-cache"));
+        assert_eq!(rule_of("assets/app.js"), None);
+        assert_eq!(rule_of("docs/service-worker.js"), None);
+
+        assert_eq!(
+            config.site.request_path("www/assets/app.js"),
+            "assets/app.js"
+        );
+        assert_eq!(
+            SiteConfig {
+                prefix: String::new(),
+                ..SiteConfig::default()
+            }
+            .request_path("app.js"),
+            "app.js"
+        );
+
+        assert!(
+            toml::from_str::<Config>(
+                "[[site.cache.rules]]
+regex = \"(\"\ncache_control = \"no-cache\""
+            )
+            .is_err()
+        );
+
+        for rule in [
+            "cache_control = \"no-cache\"",
+            "glob = \"*.js\"\nregex = \"js$\"\ncache_control = \"no-cache\"",
+            "glob = \"*.js\"\ncache_control = \"\"",
+            "glob = \"*.js\"\ncache_control = \"a\\nb\"",
+        ] {
+            let mut config = parse(&format!(
+                "[storage]\nbucket = \"main\"\n[[site.cache.rules]]\n{rule}"
+            ));
+            assert!(config.validate().is_err(), "{rule}");
+        }
+    }
+
+    /// 测试 SPA 回退策略
+    ///
+    /// 验证：
+    /// - 没有扩展名或 Accept 包含 text/html 的请求回退
+    /// - 静态资源扩展名（不区分大小写）从不回退，其他带扩展名的请求只在接受 HTML 时回退
+    /// - 关闭回退时总是不回退，回退状态码只能是 200 或 404
+    #[test]
+    fn test_fallback_policy() {
+        let fallback = FallbackConfig::default();
+        assert!(fallback.applies("app/dashboard", None));
+        assert!(fallback.applies("app/v1.2", Some("text/html,application/xhtml+xml")));
+        assert!(fallback.applies("docs/page.php", Some("Text/HTML")));
+        assert!(!fallback.applies("docs/page.php", Some("*/*")));
+        assert!(!fallback.applies("assets/app.js", Some("text/html")));
+        assert!(!fallback.applies("assets/APP.CSS", None));
+        assert_eq!(fallback.status_code(), StatusCode::OK);
+
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+            [site.fallback]
+            enabled = false
+            status = 404
+            asset_extensions = ["bin"]
+            "#,
+        );
+        config.validate().unwrap();
+        assert!(
+            !config
+                .site
+                .fallback
+                .applies("app/dashboard", Some("text/html"))
+        );
+        config.site.fallback.enabled = true;
+        assert!(
+            config
+                .site
+                .fallback
+                .applies("assets/app.js", Some("text/html"))
+        );
+        assert!(
+            !config
+                .site
+                .fallback
+                .applies("files/a.bin", Some("text/html"))
+        );
+        assert_eq!(config.site.fallback.status_code(), StatusCode::NOT_FOUND);
+
+        let mut config = parse("[storage]\nbucket = \"main\"\n[site.fallback]\nstatus = 302");
+        assert!(config.validate().is_err());
+    }
+
+    /// 测试错误页面配置
+    ///
+    /// 验证：
+    /// - `[site.error_pages]` 正确解析，未设置的字段使用默认值
+    /// - 404 页面只能是文件名，5xx 页面路径不能包含空段、`.` 或 `..`
+    #[test]
+    fn test_error_pages_config() {
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+            [site.error_pages]
+            server_error = "errors/50x.html"
+            "#,
+        );
+        config.validate().unwrap();
+        assert!(config.site.error_pages.enabled);
+        assert_eq!(config.site.error_pages.not_found, "404.html");
+        assert_eq!(config.site.error_pages.server_error, "errors/50x.html");
+
+        for pages in [
+            "not_found = \"errors/404.html\"",
+            "not_found = \"\"",
+            "server_error = \"../50x.html\"",
+            "server_error = \"errors//50x.html\"",
+        ] {
+            let mut config = parse(&format!(
+                "[storage]\nbucket = \"main\"\n[site.error_pages]\n{pages}"
+            ));
+            assert!(config.validate().is_err(), "{pages}");
+        }
+    }
+
+    /// 测试访问认证配置
+    ///
+    /// 验证：
+    /// - `[[site.auth]]` 正确解析，加载配置时读取 htpasswd 文件，路径前缀被规范化
+    /// - 规则按路径段匹配前缀，`/` 匹配整个站点
+    /// - htpasswd 文件不存在时解析失败
+    /// - 没有任何认证方式、JWT 没有密钥、realm 或 token 无效时报错
+    #[test]
+    fn test_auth_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let htpasswd = dir.path().join("htpasswd");
+        std::fs::write(&htpasswd, "alice:{SHA}5en6G6MezRroT3XKqkdPOmY/BfQ=\n").unwrap();
+        let htpasswd = htpasswd.display().to_string().replace('\\', "/");
+
+        let mut config = parse(&format!(
+            r#"
+            [storage]
+            bucket = "main"
+            [[site.auth]]
+            path = "/internal/"
+            htpasswd = "{htpasswd}"
+            [[site.auth]]
+            path = "/"
+            realm = "Staging"
+            tokens = ["t0ken"]
+            [site.auth.jwt]
+            secret = "hs-secret"
+            issuer = "https://auth.example.com"
+            "#
+        ));
+        config.validate().unwrap();
+        let rules = &config.site.auth;
+        assert_eq!(rules[0].path, "/internal");
+        assert_eq!(rules[0].realm, "Restricted");
+        assert!(
+            rules[0]
+                .htpasswd
+                .as_ref()
+                .unwrap()
+                .verify("alice", "secret")
+        );
+        assert_eq!(rules[1].jwt.as_ref().unwrap().leeway, 60);
+        assert_eq!(config.site.auth_rule("internal/a.html"), Some(&rules[0]));
+        assert_eq!(config.site.auth_rule("internal"), Some(&rules[0]));
+        assert_eq!(config.site.auth_rule("internals/a.html"), Some(&rules[1]));
+        assert_eq!(config.site.auth_rule(""), Some(&rules[1]));
+
+        let missing = "[[site.auth]]\npath = \"/\"\nhtpasswd = \"/nonexistent/htpasswd\"";
+        assert!(toml::from_str::<Config>(missing).is_err());
+
+        for rule in [
+            "path = \"/docs\"",
+            "path = \"/docs\"\n[site.auth.jwt]\nissuer = \"x\"",
+            "path = \"/docs\"\nrealm = \"a\\\"b\"\ntokens = [\"t\"]",
+            "path = \"/docs\"\ntokens = [\"\"]",
+            "path = \"/docs/../x\"\ntokens = [\"t\"]",
+        ] {
+            let mut config = parse(&format!(
+                "[storage]\nbucket = \"main\"\n[[site.auth]]\n{rule}"
+            ));
+            assert!(config.validate().is_err(), "{rule}");
+        }
+    }
+
+    /// 测试签名 URL 配置和 `sign` 子命令参数
+    ///
+    /// 验证：
+    /// - `[site.signed_urls]` 正确解析，路径前缀被规范化，未设置的时钟偏差使用默认值
+    /// - 配置了路径但没有密钥、密钥 ID 重复或无效、密钥过短时报错
+    /// - `sign` 子命令的参数正确解析，`--host` 按虚拟主机规则选择站点配置
+    #[test]
+    fn test_signed_urls_config() {
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+            [site.signed_urls]
+            paths = ["/private/"]
+            keys = [{ id = "2026-10", secret = "0123456789abcdef" }]
+            [[hosts]]
+            names = ["*.preview.example.com"]
+            [hosts.site.signed_urls]
+            paths = ["/"]
+            keys = [{ id = "preview", secret = "fedcba9876543210" }]
+            "#,
+        );
+        config.validate().unwrap();
+        let signed = &config.site.signed_urls;
+        assert_eq!(signed.paths, ["/private"]);
+        assert_eq!(signed.clock_skew, 30);
+        assert!(signed.is_protected("private/a.pdf", PathCase::Preserve));
+        assert!(!signed.is_protected("privately.pdf", PathCase::Preserve));
+        assert_eq!(
+            config
+                .site_for_host("pr-1.preview.example.com")
+                .signed_urls
+                .keys[0]
+                .id,
+            "preview"
+        );
+        assert_eq!(
+            config
+                .site_for_host("PR-1.preview.example.com.:8443")
+                .signed_urls
+                .keys[0]
+                .id,
+            "preview"
+        );
+        assert_eq!(config.site_for_host("example.com"), &config.site);
+        assert_eq!(config.site_for_host("preview.example.com"), &config.site);
+
+        for signed_urls in [
+            "paths = [\"/private\"]",
+            "keys = [{ id = \"a\", secret = \"0123456789abcdef\" }, { id = \"a\", secret = \"0123456789abcdef\" }]",
+            "keys = [{ id = \"a b\", secret = \"0123456789abcdef\" }]",
+            "keys = [{ id = \"a\", secret = \"short\" }]",
+            "paths = [\"/a/../b\"]\nkeys = [{ id = \"a\", secret = \"0123456789abcdef\" }]",
+        ] {
+            let mut config = parse(&format!(
+                "[storage]\nbucket = \"main\"\n[site.signed_urls]\n{signed_urls}"
+            ));
+            assert!(config.validate().is_err(), "{signed_urls}");
+        }
+
+        let cli = Cli::try_parse_from([
+            "static-server",
+            "--config",
+            "config.toml",
+            "sign",
+            "/private/a.pdf",
+            "--expires-in",
+            "600",
+            "--key-id",
+            "2026-10",
+        ])
+        .unwrap();
+        let Some(Command::Sign(args)) = cli.command else {
+            panic!("expected the sign command");
+        };
+        assert_eq!(args.path, "/private/a.pdf");
+        assert_eq!(args.expires_in, 600);
+        assert_eq!(args.key_id.as_deref(), Some("2026-10"));
+        assert_eq!(args.host, None);
+    }
+
+    /// 测试 CORS 配置
+    ///
+    /// 验证：
+    /// - `[[site.cors]]` 正确解析，来源、方法和头部名称被规范化，未设置的方法使用默认值
+    /// - 通配子域名匹配任意层级的子域名，不匹配域名本身、其他端口和其他协议
+    /// - 缺少来源、来源无效、凭证与 `*` 同时使用、方法或头部无效时报错
+    #[test]
+    fn test_cors_config() {
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+            [[site.cors]]
+            path = "/api/"
+            origins = ["HTTPS://App.Example.com:443/", "https://*.example.com"]
+            methods = ["get", "post"]
+            headers = ["Content-Type"]
+            credentials = true
+            max_age = 600
+            [[site.cors]]
+            path = "/"
+            origins = ["*"]
+            "#,
+        );
+        config.validate().unwrap();
+        let site = &config.site;
+        let api = site.cors_rule("api/users").unwrap();
+        assert_eq!(api.path, "/api");
+        assert_eq!(
+            api.origins,
+            ["https://app.example.com", "https://*.example.com"]
+        );
+        assert_eq!(api.methods, ["GET", "POST"]);
+        assert_eq!(api.headers, ["content-type"]);
+        assert!(api.allows_header("Content-Type"));
+        assert!(!api.allows_header("authorization"));
+        assert!(api.allows_method("POST"));
+        assert!(!api.allows_method("DELETE"));
+
+        assert!(api.allows_origin("https://app.example.com"));
+        assert!(api.allows_origin("https://a.b.Example.com"));
+        assert!(!api.allows_origin("https://example.com"));
+        assert!(!api.allows_origin("https://app.example.com:8443"));
+        assert!(!api.allows_origin("http://app.example.com"));
+        assert!(!api.allows_origin("https://evil.com/.example.com"));
+        assert!(!api.allows_origin("https://app.example.com.evil.com"));
+        assert!(!api.allows_origin("null"));
+
+        let any = site.cors_rule("index.html").unwrap();
+        assert!(any.allows_any_origin());
+        assert_eq!(any.methods, ["GET", "HEAD"]);
+        assert!(any.allows_origin("http://localhost:3000"));
+
+        for cors in [
+            "path = \"/\"\norigins = []",
+            "path = \"/\"\norigins = [\"null\"]",
+            "path = \"/\"\norigins = [\"https://example.com/app\"]",
+            "path = \"/\"\norigins = [\"ftp://example.com\"]",
+            "path = \"/\"\norigins = [\"*\"]\ncredentials = true",
+            "path = \"/\"\norigins = [\"*\"]\nmethods = [\"GET POST\"]",
+            "path = \"/\"\norigins = [\"*\"]\nheaders = [\"x y\"]",
+            "path = \"/a/../b\"\norigins = [\"*\"]",
+        ] {
+            let mut config = parse(&format!(
+                "[storage]\nbucket = \"main\"\n[[site.cors]]\n{cors}"
+            ));
+            assert!(config.validate().is_err(), "{cors}");
+        }
+    }
+
+    /// 测试虚拟主机配置
+    ///
+    /// 验证：
+    /// - `[[hosts]]` 正确解析，站点配置使用默认值补全
+    /// - 域名被规范化为小写
+    /// - 重复域名、无效通配符、同时设置存储桶和本地目录时报错
+    #[test]
+    fn test_virtual_hosts() {
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+
+            [[hosts]]
+            names = ["Example.com", "*.preview.example.com"]
+            [hosts.site]
+            prefix = "sites/example"
+
+            [[hosts]]
+            names = ["docs.example.com"]
+            bucket = "docs"
+            "#,
+        );
+        config.validate().unwrap();
+
+        assert_eq!(config.hosts.len(), 2);
+        assert_eq!(
+            config.hosts[0].names,
+            ["example.com", "*.preview.example.com"]
+        );
+        assert_eq!(config.hosts[0].site.prefix, "sites/example");
+        assert_eq!(config.hosts[1].bucket.as_deref(), Some("docs"));
+        assert_eq!(config.hosts[1].site, SiteConfig::default());
+
+        let mut duplicate = config.clone();
+        duplicate.hosts[1].names.push("example.com".to_string());
+        assert!(duplicate.validate().is_err());
+
+        for name in ["*", "a.*.example.com", "example..com", ""] {
+            let mut invalid = config.clone();
+            invalid.hosts[1].names = vec![name.to_string()];
+            assert!(invalid.validate().is_err(), "{name}");
+        }
+
+        let mut both = config.clone();
+        both.hosts[1].root = Some(PathBuf::from("./dist"));
+        assert!(both.validate().is_err());
+    }
+
+    /// 测试反向代理路由配置
+    ///
+    /// 验证：
+    /// - `[[proxy]]` 正确解析，路径前缀被规范化
+    /// - 根路径、重复路径、无效的上游 URL 报错
+    #[test]
+    fn test_proxy_routes() {
+        let mut config = parse(
+            r#"
+            [storage]
+            bucket = "main"
+
+            [[proxy]]
+            path = "/api/"
+            upstream = "http://127.0.0.1:8080/v1"
+            strip_prefix = true
+            token = "secret"
+            "#,
+        );
+        config.validate().unwrap();
+        assert_eq!(config.proxy[0].path, "/api");
+        assert!(config.proxy[0].strip_prefix);
+        assert_eq!(config.proxy[0].token.as_deref(), Some("secret"));
+
+        let mut duplicate = config.clone();
+        duplicate.proxy.push(ProxyRouteConfig {
+            path: "api".to_string(),
+            ..config.proxy[0].clone()
+        });
+        assert!(duplicate.validate().is_err());
+
+        for path in ["/", "", "/a//b", "/a/../b"] {
+            let mut invalid = config.clone();
+            invalid.proxy[0].path = path.to_string();
+            assert!(invalid.validate().is_err(), "{path}");
+        }
+
+        for upstream in ["", "127.0.0.1:8080", "ftp://host", "http://host/?a=1"] {
+            let mut invalid = config.clone();
+            invalid.proxy[0].upstream = upstream.to_string();
+            assert!(invalid.validate().is_err(), "{upstream}");
+        }
+    }
+
+    /// 测试配置文件读取错误
+    ///
+    /// 验证：
+    /// - 文件不存在时返回 Read 错误
+    /// - 文件格式错误时返回 Parse 错误并包含文件路径
+    #[test]
+    fn test_from_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.toml");
+        assert!(matches!(
+            Config::from_file(&missing),
+            Err(ConfigError::Read { .. })
+        ));
+
+        let broken = dir.path().join("broken.toml");
+        std::fs::write(&broken, "listen = 3000").unwrap();
+        let err = Config::from_file(&broken).unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        assert!(err.to_string().contains("broken.toml"));
+    }
+
+    /// 测试加载配置时按子命令校验
+    ///
+    /// 验证：
+    /// - `sign` 子命令不要求配置存储，但仍然校验站点配置
+    /// - 启动服务时未配置存储报错
+    #[test]
+    fn test_load_validates_storage_only_for_serve() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "[site.signed_urls]\npaths = [\"/private/\"]\nkeys = [{ id = \"a\", secret = \"0123456789abcdef\" }]",
+        )
+        .unwrap();
+        let config_arg = path.to_str().unwrap();
+
+        let cli = Cli::try_parse_from([
+            "static-server",
+            "--config",
+            config_arg,
+            "sign",
+            "/private/a.pdf",
+        ])
+        .unwrap();
+        let config = Config::load(&cli).unwrap();
+        assert_eq!(config.site.signed_urls.paths, ["/private"]);
+
+        let cli = Cli::try_parse_from(["static-server", "--config", config_arg]).unwrap();
+        assert!(matches!(Config::load(&cli), Err(ConfigError::Invalid(_))));
+    }
+}
@@ -0,0 +1,392 @@
+//! 跨域资源共享（CORS）
+//!
+//! 按站点的 `[[site.cors]]` 规则处理跨域请求，第一个匹配规范化后请求路径的规则生效。
+//! 没有匹配的规则时不添加任何 CORS 头，浏览器按同源策略拒绝跨域读取。
+//! 预检请求（带 `Access-Control-Request-Method` 的 OPTIONS）在认证之前直接应答：
+//! 来源、方法和请求头都被允许时返回 204 和对应的 `Access-Control-Allow-*` 头，否则返回 403。
+//! 匹配规则的响应中来自存储或上游服务的 `Access-Control-*` 头会被移除，统一按规则生成。
+
+use crate::AppState;
+use crate::config::CorsRule;
+use crate::error::builtin_error_page;
+use crate::handlers::path::normalize_path;
+use axum::extract::{Request, State};
+use axum::http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// 由 CORS 规则生成的响应头
+const CORS_RESPONSE_HEADERS: &[HeaderName] = &[
+    ACCESS_CONTROL_ALLOW_ORIGIN,
+    ACCESS_CONTROL_ALLOW_CREDENTIALS,
+    ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_EXPOSE_HEADERS,
+    ACCESS_CONTROL_MAX_AGE,
+];
+
+/// 预检请求的响应随这些请求头变化
+const PREFLIGHT_VARY: &str =
+    "origin, access-control-request-method, access-control-request-headers";
+
+/// 判断是否为 CORS 预检请求
+fn is_preflight(method: &Method, headers: &HeaderMap) -> bool {
+    method == Method::OPTIONS
+        && headers.contains_key(ORIGIN)
+        && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// 应答预检请求
+///
+/// `origin` 为已通过规则校验的请求来源，为 `None` 表示来源不被允许。
+/// 请求的方法和所有请求头都被允许时返回 204，允许的请求头按请求原样返回。
+fn preflight(rule: &CorsRule, origin: Option<&str>, headers: &HeaderMap) -> Response {
+    let method = headers
+        .get(ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let requested_headers: Vec<String> = headers
+        .get_all(ACCESS_CONTROL_REQUEST_HEADERS)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let origin = origin.filter(|_| {
+        rule.allows_method(method) && requested_headers.iter().all(|h| rule.allows_header(h))
+    });
+    let Some(origin) = origin else {
+        tracing::debug!("Rejected CORS preflight for {method} under {}", rule.path);
+        let mut response = builtin_error_page(StatusCode::FORBIDDEN);
+        response
+            .headers_mut()
+            .insert(VARY, HeaderValue::from_static(PREFLIGHT_VARY));
+        return response;
+    };
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let resp_headers = response.headers_mut();
+    resp_headers.insert(VARY, HeaderValue::from_static(PREFLIGHT_VARY));
+    insert_origin(rule, origin, resp_headers);
+    if let Ok(value) = HeaderValue::from_str(&rule.methods.join(", ")) {
+        resp_headers.insert(ACCESS_CONTROL_ALLOW_METHODS, value);
+    }
+    if !requested_headers.is_empty()
+        && let Ok(value) = HeaderValue::from_str(&requested_headers.join(", "))
+    {
+        resp_headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, value);
+    }
+    if let Some(max_age) = rule.max_age {
+        resp_headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age));
+    }
+    response
+}
+
+/// 为实际请求的响应设置 CORS 头
+///
+/// 先移除响应中已有的 `Access-Control-*` 头；规则不是任意来源时响应随 `Origin` 变化，
+/// 总是添加 `Vary: Origin`，避免缓存把一个来源的响应返回给另一个来源。
+fn apply_headers(rule: &CorsRule, origin: Option<&str>, headers: &mut HeaderMap) {
+    for name in CORS_RESPONSE_HEADERS {
+        headers.remove(name);
+    }
+    if !rule.allows_any_origin() {
+        headers.append(VARY, HeaderValue::from_static("origin"));
+    }
+    let Some(origin) = origin else {
+        return;
+    };
+    insert_origin(rule, origin, headers);
+    if !rule.expose_headers.is_empty()
+        && let Ok(value) = HeaderValue::from_str(&rule.expose_headers.join(", "))
+    {
+        headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, value);
+    }
+}
+
+/// 设置 `Access-Control-Allow-Origin` 和 `Access-Control-Allow-Credentials`
+///
+/// 任意来源的规则返回 `*`（此时不允许凭证），否则返回请求的来源。
+fn insert_origin(rule: &CorsRule, origin: &str, headers: &mut HeaderMap) {
+    let value = if rule.allows_any_origin() {
+        HeaderValue::from_static("*")
+    } else {
+        match HeaderValue::from_str(origin) {
+            Ok(value) => value,
+            Err(_) => return,
+        }
+    };
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, value);
+    if rule.credentials {
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+}
+
+/// CORS 中间件
+///
+/// 按 Host 头选择站点，规范化请求路径后查找 CORS 规则；没有匹配的规则（包括路径无效）
+/// 时不做处理。预检请求直接应答，不经过认证和文件处理；其他请求在响应中添加 CORS 头。
+pub async fn apply_cors(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let site = state
+        .hosts
+        .resolve_request(req.headers(), req.uri())
+        .config
+        .clone();
+    if site.cors.is_empty() {
+        return next.run(req).await;
+    }
+    let Ok(path) = normalize_path(req.uri().path(), site.path_case) else {
+        return next.run(req).await;
+    };
+    let Some(rule) = site.cors_rule(&path) else {
+        return next.run(req).await;
+    };
+
+    let origin = req
+        .headers()
+        .get(ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .filter(|origin| rule.allows_origin(origin))
+        .map(str::to_string);
+    if is_preflight(req.method(), req.headers()) {
+        return preflight(rule, origin.as_deref(), req.headers());
+    }
+
+    let mut response = next.run(req).await;
+    apply_headers(rule, origin.as_deref(), response.headers_mut());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SiteConfig;
+    use axum::http::header::{ALLOW, AUTHORIZATION};
+    use std::sync::Arc;
+
+    fn cors_rule(origins: &[&str]) -> CorsRule {
+        CorsRule {
+            path: "/".to_string(),
+            origins: origins.iter().map(|o| o.to_string()).collect(),
+            methods: vec!["GET".to_string(), "HEAD".to_string(), "PUT".to_string()],
+            headers: vec!["authorization".to_string(), "x-requested-with".to_string()],
+            expose_headers: vec!["etag".to_string()],
+            credentials: false,
+            max_age: Some(600),
+        }
+    }
+
+    fn preflight_headers(origin: &str, method: &str, headers: Option<&str>) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        map.insert(ORIGIN, origin.parse().unwrap());
+        map.insert(ACCESS_CONTROL_REQUEST_METHOD, method.parse().unwrap());
+        if let Some(headers) = headers {
+            map.insert(ACCESS_CONTROL_REQUEST_HEADERS, headers.parse().unwrap());
+        }
+        map
+    }
+
+    /// 测试预检请求的应答
+    ///
+    /// 验证：
+    /// - 来源、方法和请求头都被允许时返回 204，带允许的方法、请求的头部和 max-age
+    /// - 请求头的比较不区分大小写，`*` 允许任意请求头
+    /// - 方法或请求头不被允许、来源不被允许时返回 403，不带 CORS 头
+    #[test]
+    fn test_preflight() {
+        let rule = cors_rule(&["https://app.example.com"]);
+        let origin = "https://app.example.com";
+
+        let resp = preflight(
+            &rule,
+            Some(origin),
+            &preflight_headers(origin, "PUT", Some("Authorization, X-Requested-With")),
+        );
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, HEAD, PUT");
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_HEADERS],
+            "authorization, x-requested-with"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[VARY], PREFLIGHT_VARY);
+        assert!(!headers.contains_key(ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let any_header = CorsRule {
+            headers: vec!["*".to_string()],
+            ..rule.clone()
+        };
+        let resp = preflight(
+            &any_header,
+            Some(origin),
+            &preflight_headers(origin, "GET", Some("x-custom")),
+        );
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers()[ACCESS_CONTROL_ALLOW_HEADERS], "x-custom");
+
+        for (origin, method, headers) in [
+            (Some(origin), "DELETE", None),
+            (Some(origin), "put", None),
+            (Some(origin), "GET", Some("x-custom")),
+            (None, "GET", None),
+        ] {
+            let resp = preflight(
+                &rule,
+                origin,
+                &preflight_headers("https://evil.example", method, headers),
+            );
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{method} {headers:?}");
+            assert!(!resp.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+        }
+    }
+
+    /// 测试实际请求的响应头
+    ///
+    /// 验证：
+    /// - 允许的来源被原样返回，带 `Vary: Origin` 和暴露的响应头，允许凭证时带 `Allow-Credentials`
+    /// - 来源不被允许时移除已有的 CORS 头，仍带 `Vary: Origin`
+    /// - 任意来源的规则返回 `*`，不带 `Vary: Origin`
+    #[test]
+    fn test_apply_headers() {
+        let rule = CorsRule {
+            credentials: true,
+            ..cors_rule(&["https://*.example.com"])
+        };
+        let mut headers = HeaderMap::new();
+        apply_headers(&rule, Some("https://app.example.com"), &mut headers);
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[ACCESS_CONTROL_EXPOSE_HEADERS], "etag");
+        assert_eq!(headers[VARY], "origin");
+        assert!(!headers.contains_key(ACCESS_CONTROL_ALLOW_METHODS));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        apply_headers(&rule, None, &mut headers);
+        assert!(!headers.contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(headers[VARY], "origin");
+
+        let mut headers = HeaderMap::new();
+        apply_headers(&cors_rule(&["*"]), Some("https://other.test"), &mut headers);
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        assert!(!headers.contains_key(VARY));
+    }
+
+    /// 测试 CORS 中间件挂载到应用路由
+    ///
+    /// 验证：
+    /// - 未配置规则时不添加 CORS 头，OPTIONS 由文件处理器返回 204 和 `Allow` 头
+    /// - 匹配规则的预检请求在认证之前应答，不需要凭证
+    /// - 受保护路径的实际请求仍需要认证，401 和 200 响应都带 CORS 头
+    /// - 规则之外的路径不带 CORS 头
+    #[tokio::test]
+    async fn test_apply_cors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("api")).unwrap();
+        std::fs::write(dir.path().join("api/data.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("index.html"), "home").unwrap();
+        let storage = || Arc::new(crate::storage::FsStorage::new(dir.path(), "www").unwrap());
+
+        let state = AppState::new(storage(), reqwest::Client::new());
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+        let resp = server
+            .get("/api/data.json")
+            .add_header(ORIGIN, "https://app.example.com")
+            .await;
+        resp.assert_status(StatusCode::OK);
+        assert!(resp.maybe_header(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        let resp = server
+            .method(Method::OPTIONS, "/api/data.json")
+            .add_header(ORIGIN, "https://app.example.com")
+            .add_header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .await;
+        resp.assert_status(StatusCode::NO_CONTENT);
+        assert_eq!(resp.header(ALLOW), "GET, HEAD, OPTIONS");
+        assert!(resp.maybe_header(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        let site: SiteConfig = toml::from_str(
+            r#"
+            [[auth]]
+            path = "/api"
+            tokens = ["secret-token"]
+            [[cors]]
+            path = "/api"
+            origins = ["https://*.example.com"]
+            headers = ["authorization"]
+            credentials = true
+            "#,
+        )
+        .unwrap();
+        let state = AppState::with_site(storage(), reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        let resp = server
+            .method(Method::OPTIONS, "/api/data.json")
+            .add_header(ORIGIN, "https://app.example.com")
+            .add_header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .add_header(ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+            .await;
+        resp.assert_status(StatusCode::NO_CONTENT);
+        assert_eq!(
+            resp.header(ACCESS_CONTROL_ALLOW_ORIGIN),
+            "https://app.example.com"
+        );
+        assert_eq!(resp.header(ACCESS_CONTROL_ALLOW_CREDENTIALS), "true");
+        assert_eq!(resp.header(ACCESS_CONTROL_ALLOW_HEADERS), "authorization");
+
+        let resp = server
+            .get("/api/data.json")
+            .add_header(ORIGIN, "https://app.example.com")
+            .await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.header(ACCESS_CONTROL_ALLOW_ORIGIN),
+            "https://app.example.com"
+        );
+        let resp = server
+            .get("/api/data.json")
+            .add_header(ORIGIN, "https://app.example.com")
+            .add_header(AUTHORIZATION, "Bearer secret-token")
+            .await;
+        resp.assert_status(StatusCode::OK);
+        assert_eq!(
+            resp.header(ACCESS_CONTROL_ALLOW_ORIGIN),
+            "https://app.example.com"
+        );
+        assert_eq!(resp.header(VARY), "origin");
+
+        let resp = server
+            .get("/api/data.json")
+            .add_header(ORIGIN, "https://example.com")
+            .add_header(AUTHORIZATION, "Bearer secret-token")
+            .await;
+        resp.assert_status(StatusCode::OK);
+        assert!(resp.maybe_header(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        let resp = server
+            .get("/index.html")
+            .add_header(ORIGIN, "https://app.example.com")
+            .await;
+        resp.assert_status(StatusCode::OK);
+        assert!(resp.maybe_header(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+}
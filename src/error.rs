@@ -3,6 +3,7 @@
 //! 使用 thiserror 定义统一的错误类型，简化错误处理
 
 use axum::http::StatusCode;
+use axum::http::header::CONTENT_TYPE;
 use axum::response::{IntoResponse, Response};
 
 /// 应用错误类型
@@ -20,24 +21,73 @@ pub enum AppError {
     #[error("Failed to build response: {0}")]
     ResponseBuild(#[from] axum::http::Error),
 
+    /// 本地文件读取错误
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// 预签名选项无效（如有效期超出范围）
+    #[error("Invalid presign options: {0}")]
+    InvalidPresign(String),
+
+    /// 存储后端不支持预签名 URL（如本地目录存储）
+    #[error("Presigned URLs are not supported by this storage")]
+    PresignUnsupported,
+
+    /// 请求路径无效（控制字符、越过站点根目录的 `..` 等）
+    #[error("Invalid request path: {0}")]
+    InvalidPath(String),
+
     /// 文件未找到
     #[error("File not found")]
     NotFound,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        match &self {
+impl AppError {
+    /// 错误对应的 HTTP 状态码
+    pub fn status_code(&self) -> StatusCode {
+        match self {
             // 502 Bad Gateway - 上游服务错误
-            AppError::S3(_) | AppError::Http(_) => {
-                (StatusCode::BAD_GATEWAY, self.to_string()).into_response()
-            }
-            // 404 Not Found - 无响应体
-            AppError::NotFound => StatusCode::NOT_FOUND.into_response(),
+            AppError::S3(_) | AppError::Http(_) => StatusCode::BAD_GATEWAY,
+            // 400 Bad Request
+            AppError::InvalidPath(_) => StatusCode::BAD_REQUEST,
+            // 404 Not Found
+            AppError::NotFound => StatusCode::NOT_FOUND,
             // 500 Internal Server Error
-            AppError::ResponseBuild(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
-            }
+            AppError::ResponseBuild(_)
+            | AppError::Io(_)
+            | AppError::InvalidPresign(_)
+            | AppError::PresignUnsupported => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// 内置的最小错误页面
+///
+/// 只包含状态码和原因短语，不向客户端暴露内部错误信息。
+pub fn builtin_error_page(status: StatusCode) -> Response {
+    let title = format!(
+        "{} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("Error")
+    );
+    let body = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{title}</title></head>\n<body><h1>{title}</h1></body>\n</html>\n"
+    );
+    (
+        status,
+        [(CONTENT_TYPE, "text/html; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        // 详细错误只记录到日志
+        if status.is_server_error() {
+            tracing::error!("{self}");
         }
+        builtin_error_page(status)
     }
 }
@@ -1 +1,8 @@
+pub mod conditional;
+pub mod error_pages;
 pub mod files;
+pub mod path;
+pub mod precompressed;
+pub mod proxy;
+pub mod range;
+pub mod redirect;
@@ -0,0 +1,299 @@
+//! HTTP 条件请求处理
+//!
+//! 基于对象的 ETag 和 Last-Modified 评估 `If-Match`、`If-None-Match`、
+//! `If-Modified-Since`、`If-Unmodified-Since` 请求头，决定返回 304、412 还是继续处理。
+
+use crate::storage::{GetObjectOptions, ObjectMeta};
+use axum::http::HeaderMap;
+use axum::http::header::{
+    ETAG, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE, LAST_MODIFIED,
+};
+use axum::http::{HeaderName, HeaderValue};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 条件请求头列表
+pub const CONDITIONAL_HEADERS: &[HeaderName] = &[
+    IF_MATCH,
+    IF_NONE_MATCH,
+    IF_MODIFIED_SINCE,
+    IF_UNMODIFIED_SINCE,
+];
+
+/// 条件评估结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition {
+    /// 条件满足（或没有条件），继续返回内容
+    Proceed,
+    /// 客户端缓存仍然有效，返回 304
+    NotModified,
+    /// 前置条件不满足，返回 412
+    Failed,
+}
+
+/// 判断请求是否包含条件请求头
+pub fn has_preconditions(headers: &HeaderMap) -> bool {
+    CONDITIONAL_HEADERS.iter().any(|h| headers.contains_key(h))
+}
+
+/// 评估条件请求头
+///
+/// 按照 RFC 9110 第 13.2.2 节规定的顺序：
+/// 1. `If-Match` 不匹配时返回 412；没有 `If-Match` 时才检查 `If-Unmodified-Since`
+/// 2. `If-None-Match` 匹配时返回 304；没有 `If-None-Match` 时才检查 `If-Modified-Since`
+///
+/// `If-Match` 使用强比较，`If-None-Match` 使用弱比较。
+/// 日期比较精确到秒，无法解析的日期会被忽略。
+///
+/// # 参数
+///
+/// * `headers` - 客户端请求头
+/// * `meta` - 对象元数据
+pub fn evaluate_preconditions(headers: &HeaderMap, meta: &ObjectMeta) -> Precondition {
+    evaluate_conditions(&request_conditions(headers), meta)
+}
+
+/// 从请求头中提取条件参数，无法解析的日期会被忽略
+pub fn request_conditions(headers: &HeaderMap) -> GetObjectOptions {
+    GetObjectOptions {
+        if_match: header_str(headers, &IF_MATCH).map(str::to_string),
+        if_none_match: header_str(headers, &IF_NONE_MATCH).map(str::to_string),
+        if_modified_since: header_date(headers, &IF_MODIFIED_SINCE),
+        if_unmodified_since: header_date(headers, &IF_UNMODIFIED_SINCE),
+        ..GetObjectOptions::default()
+    }
+}
+
+/// 评估读取参数中的条件，规则与 `evaluate_preconditions` 相同
+///
+/// 供自行处理条件参数的存储后端（如本地目录）使用。
+pub fn evaluate_conditions(conditions: &GetObjectOptions, meta: &ObjectMeta) -> Precondition {
+    let etag = meta.etag.as_deref();
+    let last_modified = meta.last_modified.and_then(unix_secs);
+
+    if let Some(value) = &conditions.if_match {
+        if !etag_list_matches(value, etag, true) {
+            return Precondition::Failed;
+        }
+    } else if let Some(since) = conditions.if_unmodified_since.and_then(unix_secs)
+        && last_modified.is_some_and(|lm| lm > since)
+    {
+        return Precondition::Failed;
+    }
+
+    if let Some(value) = &conditions.if_none_match {
+        if etag_list_matches(value, etag, false) {
+            return Precondition::NotModified;
+        }
+    } else if let Some(since) = conditions.if_modified_since.and_then(unix_secs)
+        && last_modified.is_some_and(|lm| lm <= since)
+    {
+        return Precondition::NotModified;
+    }
+
+    Precondition::Proceed
+}
+
+/// 对象的校验头（ETag 和 Last-Modified）
+///
+/// 用于 304 响应以及本服务自行构建的响应（如 multipart/byteranges）
+pub fn validator_headers(meta: &ObjectMeta) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = Vec::new();
+    if let Some(etag) = meta
+        .etag
+        .as_deref()
+        .and_then(|e| HeaderValue::from_str(e).ok())
+    {
+        headers.push((ETAG, etag));
+    }
+    if let Some(last_modified) = meta
+        .last_modified
+        .and_then(|t| HeaderValue::from_str(&httpdate::fmt_http_date(t)).ok())
+    {
+        headers.push((LAST_MODIFIED, last_modified));
+    }
+    headers
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn header_date(headers: &HeaderMap, name: &HeaderName) -> Option<SystemTime> {
+    header_str(headers, name).and_then(|v| httpdate::parse_http_date(v.trim()).ok())
+}
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// 判断 ETag 列表（如 `"a", W/"b"` 或 `*`）是否与对象的 ETag 匹配
+///
+/// `*` 匹配任何存在的对象。强比较时两边都不能是弱 ETag。
+fn etag_list_matches(value: &str, etag: Option<&str>, strong: bool) -> bool {
+    let value = value.trim();
+    if value == "*" {
+        return true;
+    }
+    let Some(etag) = etag else {
+        return false;
+    };
+    let (etag_weak, etag_tag) = split_weak(etag);
+    if strong && etag_weak {
+        return false;
+    }
+
+    let mut rest = value;
+    while let Some((weak, tag, remaining)) = next_etag(rest) {
+        if tag == etag_tag && !(strong && weak) {
+            return true;
+        }
+        rest = remaining;
+    }
+    false
+}
+
+/// 拆分 ETag 的弱标记和带引号的值
+fn split_weak(etag: &str) -> (bool, &str) {
+    match etag.strip_prefix("W/") {
+        Some(tag) => (true, tag),
+        None => (false, etag),
+    }
+}
+
+/// 从 ETag 列表中取出下一个 ETag，返回（是否弱 ETag，带引号的值，剩余部分）
+fn next_etag(value: &str) -> Option<(bool, &str, &str)> {
+    let value = value.trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+    let (weak, value) = split_weak(value);
+    let inner = value.strip_prefix('"')?;
+    let end = inner.find('"')?;
+    Some((weak, &value[..end + 2], &inner[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn meta() -> ObjectMeta {
+        ObjectMeta {
+            content_length: 10,
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            ..ObjectMeta::default()
+        }
+    }
+
+    fn headers(pairs: &[(HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name.clone(), value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn date(secs: u64) -> String {
+        httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// 测试 If-None-Match 条件
+    ///
+    /// 验证：
+    /// - ETag 列表中任意一个匹配即返回 NotModified（弱比较）
+    /// - `*` 匹配任何对象
+    /// - 不匹配时继续处理
+    #[test]
+    fn test_if_none_match() {
+        let meta = meta();
+        for value in ["\"abc\"", "W/\"abc\"", "\"x\", \"abc\"", "*"] {
+            assert_eq!(
+                evaluate_preconditions(&headers(&[(IF_NONE_MATCH, value)]), &meta),
+                Precondition::NotModified,
+                "{value}"
+            );
+        }
+        assert_eq!(
+            evaluate_preconditions(&headers(&[(IF_NONE_MATCH, "\"x\", \"y\"")]), &meta),
+            Precondition::Proceed
+        );
+    }
+
+    /// 测试 If-Modified-Since 条件
+    ///
+    /// 验证：
+    /// - 对象未在指定时间后修改时返回 NotModified
+    /// - 存在 If-None-Match 时忽略 If-Modified-Since
+    #[test]
+    fn test_if_modified_since() {
+        let meta = meta();
+        assert_eq!(
+            evaluate_preconditions(
+                &headers(&[(IF_MODIFIED_SINCE, &date(1_700_000_000))]),
+                &meta
+            ),
+            Precondition::NotModified
+        );
+        assert_eq!(
+            evaluate_preconditions(
+                &headers(&[(IF_MODIFIED_SINCE, &date(1_600_000_000))]),
+                &meta
+            ),
+            Precondition::Proceed
+        );
+        assert_eq!(
+            evaluate_preconditions(
+                &headers(&[
+                    (IF_NONE_MATCH, "\"other\""),
+                    (IF_MODIFIED_SINCE, &date(1_700_000_000)),
+                ]),
+                &meta
+            ),
+            Precondition::Proceed
+        );
+    }
+
+    /// 测试 If-Match 和 If-Unmodified-Since 条件
+    ///
+    /// 验证：
+    /// - If-Match 使用强比较，弱 ETag 不匹配
+    /// - 对象在指定时间后被修改时返回 Failed
+    /// - If-Match 优先于 If-Unmodified-Since
+    #[test]
+    fn test_if_match_and_unmodified_since() {
+        let meta = meta();
+        assert_eq!(
+            evaluate_preconditions(&headers(&[(IF_MATCH, "\"abc\"")]), &meta),
+            Precondition::Proceed
+        );
+        assert_eq!(
+            evaluate_preconditions(&headers(&[(IF_MATCH, "W/\"abc\"")]), &meta),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate_preconditions(
+                &headers(&[(IF_UNMODIFIED_SINCE, &date(1_600_000_000))]),
+                &meta
+            ),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate_preconditions(
+                &headers(&[(IF_MATCH, "*"), (IF_UNMODIFIED_SINCE, &date(1_600_000_000)),]),
+                &meta
+            ),
+            Precondition::Proceed
+        );
+    }
+
+    /// 测试校验头的生成
+    ///
+    /// 验证：
+    /// - ETag 原样输出
+    /// - Last-Modified 使用 HTTP 日期格式
+    #[test]
+    fn test_validator_headers() {
+        let headers = validator_headers(&meta());
+        assert_eq!(headers[0], (ETAG, HeaderValue::from_static("\"abc\"")));
+        assert_eq!(headers[1].0, LAST_MODIFIED);
+        assert_eq!(headers[1].1, date(1_700_000_000).as_str());
+    }
+}
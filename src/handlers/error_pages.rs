@@ -0,0 +1,250 @@
+//! 自定义错误页面
+//!
+//! 静态文件请求失败时，从站点存储中查找错误页面并以对应的状态码返回：
+//! 404 从请求路径所在目录开始逐级向上查找 `404.html`（与 Netlify / GitHub Pages 相同），
+//! 5xx 使用站点根目录的 `50x.html`。没有找到或读取失败时返回内置的最小错误页面。
+
+use crate::AppState;
+use crate::config::SiteConfig;
+use crate::error::AppError;
+use crate::handlers::files::{guess_content_type, handle_files};
+use crate::handlers::path::normalize_path;
+use crate::site::Site;
+use crate::storage::{GetObjectOptions, Storage};
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::{Method, Response, StatusCode};
+use axum::response::IntoResponse;
+use std::sync::Arc;
+
+/// 静态文件请求的路由入口
+///
+/// 调用 `handle_files`，失败时返回站点的自定义错误页面。
+pub async fn handle_files_with_error_pages(
+    State(state): State<AppState>,
+    req: Request,
+) -> Response<Body> {
+    let Site { storage, config } = state
+        .hosts
+        .resolve_request(req.headers(), req.uri())
+        .clone();
+    // 路径无效时 handle_files 返回 400，不查找错误页面，路径不会被使用
+    let path = normalize_path(req.uri().path(), config.path_case).unwrap_or_default();
+    let is_head = req.method() == Method::HEAD;
+
+    match handle_files(State(state), req).await {
+        Ok(response) => response.into_response(),
+        // HEAD 请求没有响应体，不读取错误页面
+        Err(error) if is_head => error.into_response(),
+        Err(error) => error_response(&storage, &config, &path, error).await,
+    }
+}
+
+/// 错误页面候选的存储键（按查找顺序）
+///
+/// 404 从请求路径的父目录开始逐级向上直到站点根目录；5xx 只有站点根目录的页面；
+/// 其他状态码没有候选，使用内置页面。
+pub fn error_page_keys(site: &SiteConfig, path: &str, status: StatusCode) -> Vec<String> {
+    let pages = &site.error_pages;
+    if status == StatusCode::NOT_FOUND {
+        let mut dir = path;
+        let mut keys = Vec::new();
+        while let Some((parent, _)) = dir.rsplit_once('/') {
+            keys.push(site.object_key(&format!("{parent}/{}", pages.not_found)));
+            dir = parent;
+        }
+        keys.push(site.object_key(&pages.not_found));
+        keys
+    } else if status.is_server_error() {
+        vec![site.object_key(&pages.server_error)]
+    } else {
+        Vec::new()
+    }
+}
+
+/// 将错误转换为响应，优先使用站点的自定义错误页面
+///
+/// # 参数
+///
+/// * `storage` - 站点的存储后端
+/// * `site` - 站点配置
+/// * `path` - 请求路径（不含开头的 `/`）
+/// * `error` - 请求处理中产生的错误
+pub async fn error_response(
+    storage: &Arc<dyn Storage>,
+    site: &SiteConfig,
+    path: &str,
+    error: AppError,
+) -> Response<Body> {
+    let status = error.status_code();
+    if !site.error_pages.enabled {
+        return error.into_response();
+    }
+
+    match fetch_error_page(storage, site, path, status).await {
+        Ok(Some(response)) => {
+            if status.is_server_error() {
+                tracing::error!("{error}");
+            }
+            response
+        }
+        Ok(None) => error.into_response(),
+        Err(e) => {
+            tracing::warn!("Failed to load error page: {e}");
+            error.into_response()
+        }
+    }
+}
+
+/// 查找并读取错误页面，以指定的状态码返回
+async fn fetch_error_page(
+    storage: &Arc<dyn Storage>,
+    site: &SiteConfig,
+    path: &str,
+    status: StatusCode,
+) -> Result<Option<Response<Body>>, AppError> {
+    for key in error_page_keys(site, path, status) {
+        if !storage.check_key_exists(&key).await? {
+            continue;
+        }
+        let object = storage
+            .get_object(&key, &GetObjectOptions::default())
+            .await?;
+        if object.status != StatusCode::OK {
+            continue;
+        }
+
+        let content_type = object
+            .meta
+            .content_type
+            .clone()
+            .or_else(|| guess_content_type(&key))
+            .unwrap_or_else(|| "text/html; charset=utf-8".to_string());
+        let mut resp_builder = Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, content_type)
+            .header(CONTENT_LENGTH, object.meta.content_length)
+            .header(CACHE_CONTROL, "no-cache");
+        if let Some(content_encoding) = &object.meta.content_encoding {
+            resp_builder = resp_builder.header(CONTENT_ENCODING, content_encoding);
+        }
+        return Ok(Some(resp_builder.body(Body::from_stream(object.body))?));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{FsStorage, GetObjectOutput, MockStorage, ObjectMeta};
+    use mockall::predicate::{always, eq};
+
+    /// 测试错误页面候选键
+    ///
+    /// 验证：
+    /// - 404 从请求路径的父目录开始逐级向上查找，最后是站点根目录
+    /// - 5xx 只使用站点根目录的页面，其他状态码没有候选
+    #[test]
+    fn test_error_page_keys() {
+        let site = SiteConfig::default();
+        assert_eq!(
+            error_page_keys(&site, "docs/guide/missing.html", StatusCode::NOT_FOUND),
+            [
+                "www/docs/guide/404.html",
+                "www/docs/404.html",
+                "www/404.html"
+            ]
+        );
+        assert_eq!(
+            error_page_keys(&site, "", StatusCode::NOT_FOUND),
+            ["www/404.html"]
+        );
+        assert_eq!(
+            error_page_keys(&site, "docs/app.js", StatusCode::BAD_GATEWAY),
+            ["www/50x.html"]
+        );
+        assert!(error_page_keys(&site, "docs/app.js", StatusCode::FORBIDDEN).is_empty());
+    }
+
+    /// 测试自定义 404 页面
+    ///
+    /// 验证：
+    /// - 使用离请求路径最近的 `404.html`，状态码为 404，Content-Type 为 HTML
+    /// - 没有自定义页面或关闭错误页面时返回内置页面
+    #[tokio::test]
+    async fn test_custom_not_found_page() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("404.html"), "root 404").unwrap();
+        std::fs::write(dir.path().join("docs/404.html"), "docs 404").unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(FsStorage::new(dir.path(), "www").unwrap());
+
+        let state = AppState::new(storage.clone(), reqwest::Client::new());
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        let resp = server.get("/docs/guide/missing.js").await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(resp.header(CONTENT_TYPE), "text/html");
+        assert_eq!(resp.text(), "docs 404");
+
+        let resp = server.get("/missing.js").await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(resp.text(), "root 404");
+
+        let mut site = SiteConfig::default();
+        site.error_pages.enabled = false;
+        let state = AppState::with_site(storage, reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        let resp = server.get("/missing.js").await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(resp.header(CONTENT_TYPE), "text/html; charset=utf-8");
+        assert!(resp.text().contains("<h1>404 Not Found</h1>"));
+    }
+
+    /// 测试自定义 5xx 页面
+    ///
+    /// 验证：
+    /// - 存储错误时返回 502 和 `50x.html` 的内容
+    /// - 没有 `50x.html` 时返回内置页面，且不暴露内部错误信息
+    #[tokio::test]
+    async fn test_custom_server_error_page() {
+        let storage = |has_page: bool| {
+            let mut mock_storage = MockStorage::new();
+            mock_storage
+                .expect_get_object()
+                .with(eq("www/app.js"), always())
+                .returning(|_, _| Err(AppError::S3("secret bucket details".to_string())));
+            mock_storage
+                .expect_get_object()
+                .with(eq("www/50x.html"), always())
+                .returning(|_, _| {
+                    let meta = ObjectMeta {
+                        content_type: Some("text/html".to_string()),
+                        ..ObjectMeta::default()
+                    };
+                    Ok(GetObjectOutput::full(meta, "maintenance"))
+                });
+            mock_storage
+                .expect_check_key_exists()
+                .with(eq("www/50x.html"))
+                .returning(move |_| Ok(has_page));
+            Arc::new(mock_storage)
+        };
+
+        let state = AppState::new(storage(true), reqwest::Client::new());
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+        let resp = server.get("/app.js").await;
+        resp.assert_status(StatusCode::BAD_GATEWAY);
+        assert_eq!(resp.header(CONTENT_TYPE), "text/html");
+        assert_eq!(resp.text(), "maintenance");
+
+        let state = AppState::new(storage(false), reqwest::Client::new());
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+        let resp = server.get("/app.js").await;
+        resp.assert_status(StatusCode::BAD_GATEWAY);
+        assert!(resp.text().contains("<h1>502 Bad Gateway</h1>"));
+        assert!(!resp.text().contains("secret"));
+    }
+}
@@ -1,20 +1,36 @@
+use crate::config::{RedirectRule, SiteConfig};
 use crate::error::AppError;
-use crate::storage::Storage;
+use crate::handlers::conditional::{
+    Precondition, evaluate_preconditions, has_preconditions, validator_headers,
+};
+use crate::handlers::path::normalize_path;
+use crate::handlers::precompressed::fetch_precompressed;
+use crate::handlers::range::{
+    ByteRange, RangeRequest, generate_boundary, if_range_matches, multipart_content_type, multipart_end,
+    multipart_length, multipart_part_header, parse_range,
+};
+use crate::handlers::redirect::presigned_redirect;
+use crate::site::Site;
+use crate::storage::{GetObjectOptions, GetObjectOutput, ObjectMeta, Storage};
 use axum::http::header::{
-    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCEPT, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
     ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, AGE,
-    CACHE_CONTROL, CONNECTION, CONTENT_DISPOSITION, COOKIE, EXPIRES, HOST, ORIGIN, PRAGMA,
-    PROXY_AUTHORIZATION, REFERER, SET_COOKIE, TE, TRAILER, TRANSFER_ENCODING, UPGRADE, VARY,
+    CACHE_CONTROL, CONNECTION, CONTENT_DISPOSITION, COOKIE, EXPIRES, HOST, IF_RANGE, ORIGIN,
+    PRAGMA, PROXY_AUTHORIZATION, RANGE, REFERER, SET_COOKIE, TE, TRAILER, TRANSFER_ENCODING,
+    UPGRADE, VARY,
 };
-use axum::http::{HeaderMap, HeaderName};
+use axum::http::{HeaderMap, HeaderName, Method};
 use axum::{
     body::Body,
     extract::{Request, State},
     http::{Response, StatusCode, header},
     response::IntoResponse,
 };
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt, future, stream};
 use mime_guess::MimeGuess;
 use std::path::Path;
+use std::sync::Arc;
 
 /// 请求头黑名单（需要在代理转发时移除的头）
 ///
@@ -42,7 +58,7 @@ pub const REQUEST_HEADERS_BLOCKLIST: &[HeaderName] = &[
 ///
 /// 这些头字段在返回给客户端时应该被移除，因为：
 /// - CONNECTON, TE, TRAILER, TRANSFER_ENCODING, UPGRADE: HTTP 连接/传输相关头，由代理服务器处理
-/// - ACCESS_CONTROL_*: CORS 相关头，应由代理服务器的 CORS 层统一管理，避免冲突
+/// - ACCESS_CONTROL_*: CORS 相关头，由 CORS 中间件按站点规则统一生成，避免冲突
 /// - VARY, SET_COOKIE, CACHE_CONTROL, EXPIRES, AGE: 缓存和 Cookie 相关头，可能会影响客户端行为
 pub const RESPONSE_HEADERS_BLOCKLIST: &[HeaderName] = &[
     CONNECTION,                       // 连接控制，由代理服务器管理
@@ -50,13 +66,13 @@ pub const RESPONSE_HEADERS_BLOCKLIST: &[HeaderName] = &[
     TRAILER,                          // 尾部字段，由代理服务器处理
     TRANSFER_ENCODING,                // 传输编码，由代理服务器处理
     UPGRADE,                          // 协议升级，代理服务器不支持
-    ACCESS_CONTROL_ALLOW_ORIGIN,      // CORS 头，由代理服务器的 CorsLayer 管理
-    ACCESS_CONTROL_ALLOW_METHODS,     // CORS 头，由代理服务器的 CorsLayer 管理
-    ACCESS_CONTROL_ALLOW_HEADERS,     // CORS 头，由代理服务器的 CorsLayer 管理
-    ACCESS_CONTROL_ALLOW_CREDENTIALS, // CORS 头，由代理服务器的 CorsLayer 管理
-    ACCESS_CONTROL_EXPOSE_HEADERS,    // CORS 头，由代理服务器的 CorsLayer 管理
-    ACCESS_CONTROL_MAX_AGE,           // CORS 头，由代理服务器的 CorsLayer 管理
-    VARY,                             // 内容协商，可能会影响缓存
+    ACCESS_CONTROL_ALLOW_ORIGIN,      // CORS 头，由 CORS 中间件按站点规则生成
+    ACCESS_CONTROL_ALLOW_METHODS,     // CORS 头，由 CORS 中间件按站点规则生成
+    ACCESS_CONTROL_ALLOW_HEADERS,     // CORS 头，由 CORS 中间件按站点规则生成
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, // CORS 头，由 CORS 中间件按站点规则生成
+    ACCESS_CONTROL_EXPOSE_HEADERS,    // CORS 头，由 CORS 中间件按站点规则生成
+    ACCESS_CONTROL_MAX_AGE,           // CORS 头，由 CORS 中间件按站点规则生成
+    VARY,                             // 内容协商，可能会影响缓存（Accept-Encoding 由压缩层重新设置）
     SET_COOKIE,                       // Cookie 设置，应由代理服务器管理
     CACHE_CONTROL,                    // 缓存控制，应由代理服务器管理
     EXPIRES,                          // 过期时间，应由代理服务器管理
@@ -159,18 +175,37 @@ pub async fn proxy_request(
     Ok(builder.body(body)?)
 }
 
-/// S3 存储桶中的 www 前缀
-pub const WWW_PREFIX: &str = "www";
-
-/// 默认的索引文件名
-pub const INDEX_FILE: &str = "index.html";
+/// 静态文件处理器支持的请求方法，用于 OPTIONS 和 405 响应的 Allow 头
+pub const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
 
 /// 不应缓存的文件扩展名。
 pub const NO_CACHE_EXTS: &[&str] = &["html", "htm"];
 
-/// 缓存控制头部值（30 天缓存，适用于 CSS、JS、图片等静态资源）
-/// max-age=2592000 表示 2592000 秒 = 30 天
-pub const CACHE_CONTROL_VALUE: &str = "public, max-age=2592000";
+/// 未带内容哈希的静态资源（如 `favicon.ico`）的默认缓存时间（1 小时），过期后需要重新验证
+pub const CACHE_MAX_AGE: u64 = 3600;
+
+/// 文件名带内容哈希的资源使用的 Cache-Control：内容变化时文件名随之变化，可以永久缓存
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// 默认的内容哈希文件名匹配模式
+///
+/// 匹配 `.` 或 `-` 之后、扩展名之前的哈希段：
+/// - 8 位以上的十六进制（webpack / Parcel 的 `main.3f9a1c2b.js`、旧版 Vite 的 `index-3f9a1c2b.js`）
+/// - 8 位 base64url，且首字符之后含有大写字母、或任意位置含有数字、`_`、`-`
+///   （Vite / Rollup / esbuild 的 `index-BxA1_c9Z.js`），避免把 `app.minified.js`、
+///   `icon-Settings.svg` 这样的普通单词当作哈希
+pub const DEFAULT_HASHED_FILENAME_PATTERN: &str = concat!(
+    r"[.-](?:[0-9a-f]{8,}",
+    r"|[0-9_-][A-Za-z0-9_-]{7}",
+    r"|[A-Za-z][A-Z0-9_-][A-Za-z0-9_-]{6}",
+    r"|[A-Za-z][a-z][A-Z0-9_-][A-Za-z0-9_-]{5}",
+    r"|[A-Za-z][a-z]{2}[A-Z0-9_-][A-Za-z0-9_-]{4}",
+    r"|[A-Za-z][a-z]{3}[A-Z0-9_-][A-Za-z0-9_-]{3}",
+    r"|[A-Za-z][a-z]{4}[A-Z0-9_-][A-Za-z0-9_-]{2}",
+    r"|[A-Za-z][a-z]{5}[A-Z0-9_-][A-Za-z0-9_-]",
+    r"|[A-Za-z][a-z]{6}[A-Z0-9_-]",
+    r")(?:\.[A-Za-z0-9]+)+$",
+);
 
 /// 根据文件扩展名判断是否应该缓存该文件
 ///
@@ -181,93 +216,305 @@ pub const CACHE_CONTROL_VALUE: &str = "public, max-age=2592000";
 /// # 参数
 ///
 /// * `key` - 文件路径或文件名
+/// * `no_cache_exts` - 不缓存的文件扩展名（不区分大小写），默认为 `NO_CACHE_EXTS`
 ///
 /// # 返回值
 ///
 /// 如果文件应该被缓存则返回 `true`，否则返回 `false`
-pub fn should_cache(key: &str) -> bool {
+pub fn should_cache(key: &str, no_cache_exts: &[impl AsRef<str>]) -> bool {
     let ext = Path::new(key)
         .extension()
         .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase())
         .unwrap_or_default();
-    !NO_CACHE_EXTS.contains(&ext.as_str())
+    !no_cache_exts
+        .iter()
+        .any(|e| e.as_ref().eq_ignore_ascii_case(ext))
+}
+
+/// 根据站点的缓存配置计算文件的 Cache-Control 头值
+///
+/// 优先级从高到低：
+/// 1. 开启 `trust_object_cache_control` 时对象自身的 Cache-Control 元数据
+/// 2. 第一个匹配请求路径的缓存规则
+/// 3. 不缓存的扩展名（如 HTML）返回 `no-cache`
+/// 4. 文件名带内容哈希时返回 `IMMUTABLE_CACHE_CONTROL`
+/// 5. 其余返回较短的 `public, max-age=..., must-revalidate`
+pub(crate) fn cache_control(site: &SiteConfig, key: &str, meta: &ObjectMeta) -> String {
+    let cache = &site.cache;
+    if cache.trust_object_cache_control
+        && let Some(cache_control) = &meta.cache_control
+    {
+        return cache_control.clone();
+    }
+    if let Some(rule) = cache.rule(site.request_path(key)) {
+        return rule.cache_control.clone();
+    }
+    if !should_cache(key, &cache.no_cache_extensions) {
+        return "no-cache".to_string();
+    }
+    if cache.is_hashed(key) {
+        return IMMUTABLE_CACHE_CONTROL.to_string();
+    }
+    cache.cache_control_value()
+}
+
+/// 根据文件键推断 Content-Type
+pub(crate) fn guess_content_type(key: &str) -> Option<String> {
+    MimeGuess::from_path(key).first().map(|m| m.to_string())
+}
+
+/// 将存储读取结果转换为客户端响应
+///
+/// 根据对象元数据设置 Content-Type、Content-Encoding、校验头、Content-Length /
+/// Content-Range、Accept-Ranges 和缓存头，并以流的形式转发响应体。
+fn object_response(
+    object: GetObjectOutput,
+    key: &str,
+    cache_key: &str,
+    site: &SiteConfig,
+) -> Result<Response<Body>, AppError> {
+    let GetObjectOutput {
+        status,
+        meta,
+        range,
+        body,
+    } = object;
+
+    if !status.is_success() {
+        return Ok(Response::builder().status(status).body(Body::empty())?);
+    }
+
+    let mut resp_builder = Response::builder()
+        .status(status)
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    for (name, value) in validator_headers(&meta) {
+        resp_builder = resp_builder.header(name, value);
+    }
+
+    if let Some(content_type) = meta.content_type.clone().or_else(|| guess_content_type(key)) {
+        resp_builder = resp_builder.header(header::CONTENT_TYPE, content_type);
+    }
+
+    if let Some(content_encoding) = &meta.content_encoding {
+        resp_builder = resp_builder.header(header::CONTENT_ENCODING, content_encoding);
+    }
+
+    resp_builder = match range {
+        Some((start, end)) => {
+            let range = ByteRange { start, end };
+            resp_builder
+                .header(header::CONTENT_RANGE, range.content_range(meta.content_length))
+                .header(header::CONTENT_LENGTH, range.len())
+        }
+        None => resp_builder.header(header::CONTENT_LENGTH, meta.content_length),
+    };
+
+    resp_builder = resp_builder.header(header::CACHE_CONTROL, cache_control(site, cache_key, &meta));
+
+    // 流式传输响应体：按需从存储拉取数据块，由客户端消费速度形成背压，
+    // 避免大文件整体加载到内存
+    Ok(resp_builder.body(Body::from_stream(body))?)
 }
 
 /// 从对象存储获取文件并返回 HTTP 响应
 ///
-/// 该函数通过预签名 URL 从 S3 兼容的对象存储中获取文件内容，
-/// 并将其作为 HTTP 响应返回给客户端。支持请求头和响应头过滤，
-/// 自动检测 Content-Type，并根据文件类型设置缓存策略。
+/// 该函数通过 `Storage::get_object` 从 S3 兼容的对象存储（或本地目录）中读取文件内容，
+/// 并将其作为 HTTP 响应返回给客户端。自动检测 Content-Type，并根据文件类型设置缓存策略。
 ///
 /// # 参数
 ///
-/// * `storage` - 存储后端 trait 对象，用于获取预签名 URL
-/// * `http_client` - reqwest HTTP 客户端，用于发送请求
-/// * `headers` - 客户端传入的请求头，会被过滤后转发
+/// * `storage` - 存储后端 trait 对象
+/// * `headers` - 客户端传入的请求头，用于 Range 和条件请求
 /// * `key` - 文件在存储中的键（路径）
+/// * `site` - 站点配置，用于计算缓存策略
 ///
 /// # 返回值
 ///
-/// * `Ok(Response)` - 包含文件内容的 HTTP 响应
+/// * `Ok(Response)` - 包含文件内容的 HTTP 响应，文件不存在时状态码为 404
 /// * `Err(AppError)` - 获取失败时返回错误
 ///
 /// # 功能特性
 ///
-/// 1. **预签名 URL**: 使用存储后端生成临时访问 URL
-/// 2. **请求头过滤**: 转发前移除黑名单中的请求头
-/// 3. **Content-Type 检测**: 自动根据文件扩展名推断 MIME 类型
-/// 4. **缓存控制**: 根据文件类型自动设置缓存头
+/// 1. **直接读取**: 使用存储 SDK 读取对象，享有 SDK 的重试、凭证刷新和类型化错误
+/// 2. **Content-Type 检测**: 存储中没有记录时根据文件扩展名推断 MIME 类型
+/// 3. **缓存控制**: 根据对象元数据、缓存规则和文件类型设置缓存头
+/// 4. **流式传输**: 响应体直接以流的形式转发，内存占用与文件大小无关
+/// 5. **Range 请求**: 支持单区间（206）、多区间（multipart/byteranges）、
+///    If-Range 以及无法满足时的 416 响应
+/// 6. **条件请求**: 根据 ETag / Last-Modified 返回 304 或 412，无需下载对象内容
 pub async fn fetch_and_proxy_file(
-    storage: &dyn Storage,
-    http_client: &reqwest::Client,
-    headers: &http::HeaderMap,
+    storage: &Arc<dyn Storage>,
+    headers: &HeaderMap,
     key: &str,
+    site: &SiteConfig,
 ) -> Result<Response<Body>, AppError> {
-    let presigned_url = storage.get_presigned_url(key).await?;
+    fetch_file_as(storage, &Method::GET, headers, key, key, site).await
+}
 
-    let forwarded_headers = {
-        let mut result = http::HeaderMap::new();
-        for (name, value) in headers.iter() {
-            if !REQUEST_HEADERS_BLOCKLIST.contains(name) {
-                result.insert(name.clone(), value.clone());
+/// 获取文件，并按另一个键（`cache_key`）计算缓存头
+///
+/// 用于预压缩文件：内容读取自 `app.js.br`，缓存规则和扩展名按原文件 `app.js` 匹配。
+/// HEAD 请求只获取对象元数据，返回与 GET 相同的响应头（忽略 Range），不读取对象内容。
+pub(crate) async fn fetch_file_as(
+    storage: &Arc<dyn Storage>,
+    method: &Method,
+    headers: &HeaderMap,
+    key: &str,
+    cache_key: &str,
+    site: &SiteConfig,
+) -> Result<Response<Body>, AppError> {
+    let head_only = method == Method::HEAD;
+    let range = headers.get(RANGE).and_then(|v| v.to_str().ok());
+
+    if !head_only && range.is_none() && !has_preconditions(headers) {
+        let object = storage.get_object(key, &GetObjectOptions::default()).await?;
+        return object_response(object, key, cache_key, site);
+    }
+
+    // HEAD、条件请求和 Range 请求需要先获取对象元数据，304 / 412 / 416 均无需下载对象内容
+    let Some(meta) = storage.head(key).await? else {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())?);
+    };
+
+    match evaluate_preconditions(headers, &meta) {
+        Precondition::Proceed => {}
+        Precondition::NotModified => {
+            let mut resp_builder = Response::builder().status(StatusCode::NOT_MODIFIED);
+            for (name, value) in validator_headers(&meta) {
+                resp_builder = resp_builder.header(name, value);
             }
+            resp_builder =
+                resp_builder.header(header::CACHE_CONTROL, cache_control(site, cache_key, &meta));
+            return Ok(resp_builder.body(Body::empty())?);
         }
-        result
-    };
+        Precondition::Failed => {
+            return Ok(Response::builder()
+                .status(StatusCode::PRECONDITION_FAILED)
+                .body(Body::empty())?);
+        }
+    }
 
-    // 使用 reqwest 直接发送 GET 请求
-    let resp = http_client
-        .get(&presigned_url)
-        .headers(forwarded_headers)
-        .send()
-        .await?;
+    if head_only {
+        let object = GetObjectOutput {
+            status: StatusCode::OK,
+            meta,
+            range: None,
+            body: stream::empty().boxed(),
+        };
+        return object_response(object, key, cache_key, site);
+    }
 
-    let status = resp.status();
-    let response_headers = resp.headers().clone();
-    let body = resp.bytes().await?.to_vec();
+    fetch_range(storage, headers, key, cache_key, site, &meta, range).await
+}
 
-    let mut resp_builder = Response::builder().status(status);
+/// 在已获取对象元数据的前提下获取文件内容
+///
+/// 根据 Range 和 If-Range 决定返回完整内容、单区间、多区间还是 416。
+/// 读取时以元数据中的 ETag 作为 `If-Match` 条件，避免对象在两次请求之间被修改后
+/// 返回与元数据不一致的内容。
+async fn fetch_range(
+    storage: &Arc<dyn Storage>,
+    headers: &HeaderMap,
+    key: &str,
+    cache_key: &str,
+    site: &SiteConfig,
+    meta: &ObjectMeta,
+    range: Option<&str>,
+) -> Result<Response<Body>, AppError> {
+    let size = meta.content_length;
+
+    let if_range_ok = headers
+        .get(IF_RANGE)
+        .map(|v| v.to_str().is_ok_and(|v| if_range_matches(v, meta)))
+        .unwrap_or(true);
+    let request = match range {
+        Some(range) if if_range_ok => parse_range(range, size),
+        _ => RangeRequest::Full,
+    };
 
-    for (name, value) in response_headers.iter() {
-        if RESPONSE_HEADERS_BLOCKLIST.contains(name) {
-            continue;
+    let ranges = match request {
+        RangeRequest::Unsatisfiable => {
+            return Ok(Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{size}"))
+                .header(header::ACCEPT_RANGES, "bytes")
+                .body(Body::empty())?);
         }
-        resp_builder = resp_builder.header(name, value);
-    }
+        RangeRequest::Full => Vec::new(),
+        RangeRequest::Partial(ranges) => ranges,
+    };
 
-    if !response_headers.contains_key(header::CONTENT_TYPE) {
-        if let Some(guessed_content_type) = MimeGuess::from_path(key).first().map(|m| m.to_string())
-        {
-            resp_builder = resp_builder.header(header::CONTENT_TYPE, guessed_content_type);
+    let etag = meta.etag.clone();
+    let options = move |range: Option<ByteRange>| GetObjectOptions {
+        range: range.map(|r| (r.start, r.end)),
+        if_match: etag.clone(),
+        ..GetObjectOptions::default()
+    };
+
+    if ranges.len() <= 1 {
+        let object = storage.get_object(key, &options(ranges.first().copied())).await?;
+        if !object.status.is_success() {
+            return Err(unexpected_status(object.status, key));
         }
+        return object_response(object, key, cache_key, site);
     }
 
-    if status.is_success() && should_cache(key) {
-        resp_builder = resp_builder.header(header::CACHE_CONTROL, CACHE_CONTROL_VALUE);
+    // 多区间：逐个从存储读取区间内容，拼接为 multipart/byteranges 响应体
+    let content_type = meta
+        .content_type
+        .clone()
+        .or_else(|| guess_content_type(key))
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let boundary = generate_boundary();
+    let content_length = multipart_length(&boundary, &content_type, &ranges, size);
+    let end = Bytes::from(multipart_end(&boundary));
+    let response_content_type = multipart_content_type(&boundary);
+
+    let storage = storage.clone();
+    let key_owned = key.to_string();
+    let parts = stream::iter(ranges)
+        .then(move |range| {
+            let storage = storage.clone();
+            let key = key_owned.clone();
+            let options = options(Some(range));
+            let part_header = Bytes::from(multipart_part_header(
+                &boundary,
+                &content_type,
+                &range,
+                size,
+            ));
+            async move {
+                let object = storage.get_object(&key, &options).await?;
+                if object.status != StatusCode::PARTIAL_CONTENT {
+                    return Err(unexpected_status(object.status, &key));
+                }
+                Ok(stream::once(future::ready(Ok(part_header))).chain(object.body))
+            }
+        })
+        .try_flatten()
+        .chain(stream::once(future::ready(Ok(end))));
+
+    let mut resp_builder = Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header(header::CONTENT_TYPE, response_content_type)
+        .header(header::CONTENT_LENGTH, content_length)
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    for (name, value) in validator_headers(meta) {
+        resp_builder = resp_builder.header(name, value);
     }
 
-    Ok(resp_builder.body(Body::from(body))?)
+    resp_builder = resp_builder.header(header::CACHE_CONTROL, cache_control(site, cache_key, meta));
+
+    Ok(resp_builder.body(Body::from_stream(parts))?)
+}
+
+/// 已确认存在的对象读取失败（如在两次请求之间被修改或删除）
+fn unexpected_status(status: StatusCode, key: &str) -> AppError {
+    AppError::S3(format!("Unexpected status {status} when reading '{key}'"))
 }
 
 /// 查找存在的文件键（支持单页应用路由回退）
@@ -281,9 +528,12 @@ pub async fn fetch_and_proxy_file(
 /// 2. `{parent_path}/index.html` - 逐级向上查找父目录索引
 /// 3. `index.html` - 根目录索引（最终回退）
 ///
+/// 配置了多个索引文件时，每一级目录都按配置顺序依次尝试所有候选文件。
+///
 /// # 参数
 ///
 /// * `storage` - 存储后端 trait 对象
+/// * `site` - 站点配置，提供存储前缀和索引文件列表
 /// * `pathname` - 请求的路径名
 ///
 /// # 返回值
@@ -298,43 +548,50 @@ pub async fn fetch_and_proxy_file(
 /// - 最后尝试 `www/index.html`
 pub async fn find_exists_key(
     storage: &dyn Storage,
+    site: &SiteConfig,
     pathname: &str,
 ) -> Result<Option<String>, AppError> {
-    let dir_index = format!("{WWW_PREFIX}/{}/{INDEX_FILE}", pathname);
-    match storage.check_key_exists(&dir_index).await {
-        Ok(true) => return Ok(Some(dir_index)),
-        Ok(false) => {}
-        Err(e) => return Err(e),
-    }
-
-    let parts: Vec<&str> = pathname.split('/').collect();
-    for i in (1..parts.len()).rev() {
-        let parent_path = parts[..i].join("/");
-        let index_key = format!("{WWW_PREFIX}/{}/{INDEX_FILE}", parent_path);
-        match storage.check_key_exists(&index_key).await {
-            Ok(true) => return Ok(Some(index_key)),
-            Ok(false) => {}
-            Err(e) => return Err(e),
+    // 从请求路径本身开始逐级向上，最后是根目录（空路径）
+    let parts: Vec<&str> = if pathname.is_empty() {
+        Vec::new()
+    } else {
+        pathname.split('/').collect()
+    };
+    for i in (0..=parts.len()).rev() {
+        let dir = parts[..i].join("/");
+        if let Some(index_key) = find_index_key(storage, site, &dir).await? {
+            return Ok(Some(index_key));
         }
     }
 
-    let root_index = format!("{WWW_PREFIX}/{INDEX_FILE}");
-    match storage.check_key_exists(&root_index).await {
-        Ok(true) => Ok(Some(root_index)),
-        Ok(false) => Ok(None),
-        Err(e) => Err(e),
+    Ok(None)
+}
+
+/// 查找目录的索引文件（按配置顺序尝试所有候选文件），不向上级目录回退
+///
+/// `dir` 为空表示站点根目录。
+pub async fn find_index_key(
+    storage: &dyn Storage,
+    site: &SiteConfig,
+    dir: &str,
+) -> Result<Option<String>, AppError> {
+    for index_key in site.index_keys(dir) {
+        if storage.check_key_exists(&index_key).await? {
+            return Ok(Some(index_key));
+        }
     }
+    Ok(None)
 }
 
 /// 处理静态文件请求的主入口
 ///
-/// 这是处理文件请求的 Axum handler。它从请求路径中提取文件路径，
-/// 尝试从对象存储获取文件。如果文件不存在，则尝试使用 SPA 回退机制
+/// 这是处理文件请求的 Axum handler。它先根据 Host 头选择站点，再从请求路径中提取文件路径，
+/// 尝试从站点的对象存储获取文件。如果文件不存在，则尝试使用 SPA 回退机制
 /// 查找合适的 index.html 文件。
 ///
 /// # 参数
 ///
-/// * `state` - 应用状态，包含存储后端、HTTP 客户端和存储桶名称
+/// * `state` - 应用状态，包含虚拟主机表（各站点的存储后端和配置）和 HTTP 客户端
 /// * `req` - HTTP 请求对象
 ///
 /// # 返回值
@@ -344,69 +601,178 @@ pub async fn find_exists_key(
 ///
 /// # 处理流程
 ///
-/// 1. 提取并清理请求路径
+/// 0. OPTIONS 返回 204，其他不支持的方法返回 405，均带 `Allow` 头；
+///    HEAD 只获取对象元数据，不读取对象内容
+/// 1. 规范化请求路径，无效路径返回 400
 /// 2. 尝试直接获取请求的文件
-/// 3. 如果返回 404，则尝试查找存在的 index.html（SPA 回退）
-/// 4. 返回找到的文件内容或 404 错误
+/// 3. 如果返回 404，则尝试请求路径作为目录时的索引文件
+/// 4. 仍不存在且请求像页面导航（见 `FallbackConfig`）时，向上级目录查找索引文件（SPA 回退），
+///    回退响应使用配置的状态码（200 或 404）
+/// 5. 返回找到的文件内容或 404 错误
 ///
 /// # 路径处理
 ///
-/// - 去除前导和尾随斜杠
-/// - 空路径返回 404
-/// - 自动添加 `www/` 前缀到存储键
+/// - 百分号解码，去除前导、尾随和重复的斜杠及 `.` 段，解析 `..`（见 `normalize_path`）
+/// - 按站点配置处理大小写
+/// - 空路径（站点根目录 `/`）直接返回根目录的索引文件；
+///   关闭 `serve_root` 时返回 404（适用于仅提供深层路径的部署）
+/// - 自动添加站点配置的前缀（默认 `www/`）到存储键
 pub async fn handle_files(
     State(state): State<crate::AppState>,
     req: Request,
 ) -> Result<impl IntoResponse, AppError> {
-    let path = req
-        .uri()
-        .path()
-        .trim_start_matches('/')
-        .trim_end_matches('/');
-
-    if path.is_empty() || path.trim().is_empty() {
-        return Err(AppError::NotFound);
+    let method = req.method();
+    if method != Method::GET && method != Method::HEAD {
+        let status = if method == Method::OPTIONS {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::METHOD_NOT_ALLOWED
+        };
+        return Ok(Response::builder()
+            .status(status)
+            .header(header::ALLOW, ALLOWED_METHODS)
+            .body(Body::empty())?);
     }
 
-    let s3_path = format!("{WWW_PREFIX}/{path}");
+    let Site {
+        storage,
+        config: site,
+    } = state.hosts.resolve_request(req.headers(), req.uri());
+    let site = site.as_ref();
+    let path = normalize_path(req.uri().path(), site.path_case)?;
+    let path = path.as_str();
+    let redirect = site.redirect_rule(path);
+    let mime_types = state.hosts.compressible_types.as_slice();
+
+    if path.is_empty() {
+        if !site.serve_root {
+            return Err(AppError::NotFound);
+        }
+    } else {
+        if path.trim().is_empty() {
+            return Err(AppError::NotFound);
+        }
 
-    let response = fetch_and_proxy_file(
-        state.storage.as_ref(),
-        &state.http_client,
-        req.headers(),
-        &s3_path,
-    )
-    .await?;
+        let s3_path = site.object_key(path);
+
+        if redirect.is_some() {
+            // 重定向前需要确认对象存在，否则客户端会被重定向到对象存储的 404
+            if storage.check_key_exists(&s3_path).await? {
+                return serve_file(
+                    storage,
+                    method,
+                    req.headers(),
+                    &s3_path,
+                    site,
+                    redirect,
+                    mime_types,
+                )
+                .await;
+            }
+        } else {
+            let response = serve_file(
+                storage,
+                method,
+                req.headers(),
+                &s3_path,
+                site,
+                None,
+                mime_types,
+            )
+            .await?;
+
+            if response.status() != StatusCode::NOT_FOUND {
+                return Ok(response);
+            }
+        }
+    }
 
-    if response.status() != StatusCode::NOT_FOUND {
-        return Ok(response);
+    // 目录索引
+    if let Some(index_key) = find_index_key(storage.as_ref(), site, path).await? {
+        return serve_file(
+            storage,
+            method,
+            req.headers(),
+            &index_key,
+            site,
+            redirect,
+            mime_types,
+        )
+        .await;
     }
 
-    let file_key = find_exists_key(state.storage.as_ref(), path)
+    // SPA 回退：只对页面导航请求回退到上级目录的索引文件
+    let accept = req.headers().get(ACCEPT).and_then(|v| v.to_str().ok());
+    if path.is_empty() || !site.fallback.applies(path, accept) {
+        return Err(AppError::NotFound);
+    }
+    let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    let file_key = find_exists_key(storage.as_ref(), site, parent)
         .await?
         .ok_or(AppError::NotFound)?;
 
-    fetch_and_proxy_file(
-        state.storage.as_ref(),
-        &state.http_client,
+    let mut response = serve_file(
+        storage,
+        method,
         req.headers(),
         &file_key,
+        site,
+        redirect,
+        mime_types,
     )
-    .await
+    .await?;
+    if response.status() == StatusCode::OK {
+        *response.status_mut() = site.fallback.status_code();
+    }
+    Ok(response)
+}
+
+/// 返回存储中的文件
+///
+/// 匹配重定向规则时返回指向预签名 URL 的重定向；站点开启预压缩时优先返回客户端接受的
+/// `.br` / `.gz` 同名文件（`mime_types` 为可压缩的 MIME 类型白名单）；否则转发原文件。
+async fn serve_file(
+    storage: &Arc<dyn Storage>,
+    method: &Method,
+    headers: &HeaderMap,
+    key: &str,
+    site: &SiteConfig,
+    redirect: Option<&RedirectRule>,
+    mime_types: &[String],
+) -> Result<Response<Body>, AppError> {
+    if let Some(rule) = redirect
+        && let Some(response) = presigned_redirect(storage.as_ref(), key, rule).await?
+    {
+        return Ok(response);
+    }
+    if site.precompressed
+        && let Some(response) =
+            fetch_precompressed(storage, method, headers, key, site, mime_types).await?
+    {
+        return Ok(response);
+    }
+    fetch_file_as(storage, method, headers, key, key, site).await
 }
 
 #[cfg(test)]
 mod tests {
     use crate::handlers::files::{
-        fetch_and_proxy_file, find_exists_key, handle_files, proxy_request, should_cache, CONNECTION,
-        HOST,
+        cache_control, fetch_and_proxy_file, find_exists_key, handle_files, proxy_request,
+        should_cache, CONNECTION, HOST, IMMUTABLE_CACHE_CONTROL, NO_CACHE_EXTS,
     };
-    use crate::storage::MockStorage;
+    use crate::config::SiteConfig;
+    use crate::site::{Site, VirtualHosts};
+    use crate::storage::{GetObjectOptions, GetObjectOutput, MockStorage, ObjectMeta, Storage};
     use crate::AppState;
     use axum::body::Body;
-    use axum::http::{HeaderMap, Request, StatusCode};
+    use axum::http::header::{
+        ALLOW, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, LAST_MODIFIED, RANGE,
+    };
+    use axum::http::{HeaderMap, Method, Request, StatusCode};
     use axum::response::IntoResponse;
-    use mockall::predicate::eq;
+    use bytes::Bytes;
+    use futures::{StreamExt, stream};
+    use mockall::predicate::{always, eq};
     use std::sync::Arc;
     use wiremock::matchers::{header, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -583,38 +949,423 @@ mod tests {
     /// 测试 fetch_and_proxy_file 函数的文件获取功能
     ///
     /// 验证：
-    /// - 能够成功从存储获取预签名 URL
-    /// - 通过预签名 URL 获取文件内容
-    /// - 返回正确的 HTTP 响应状态码（200 OK）
+    /// - 通过存储的 get_object 读取文件内容，返回 200 OK
+    /// - Content-Type、Content-Encoding 和 ETag 取自对象元数据
+    /// - Content-Length 为对象大小，并带有 Accept-Ranges
     #[tokio::test]
     async fn test_fetch_and_proxy_file_success() {
-        let mock_server = MockServer::start().await;
         let mut mock_storage = MockStorage::new();
-        let mock_uri = mock_server.uri();
+        mock_storage
+            .expect_get_object()
+            .with(eq("www/test.txt"), eq(GetObjectOptions::default()))
+            .returning(|_, _| {
+                Ok(GetObjectOutput::full(
+                    ObjectMeta {
+                        etag: Some("\"v1\"".to_string()),
+                        content_type: Some("text/plain".to_string()),
+                        content_encoding: Some("gzip".to_string()),
+                        ..ObjectMeta::default()
+                    },
+                    "Hello World",
+                ))
+            });
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
 
-        Mock::given(method("GET"))
-            .and(path("/test.txt"))
-            .respond_with(ResponseTemplate::new(200).set_body_string("Hello World"))
-            .mount(&mock_server)
-            .await;
+        let response = fetch_and_proxy_file(
+            &storage,
+            &HeaderMap::new(),
+            "www/test.txt",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
 
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/plain");
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        assert_eq!(response.headers()["etag"], "\"v1\"");
+        assert_eq!(response.headers()["content-length"], "11");
+        assert_eq!(response.headers()["accept-ranges"], "bytes");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "Hello World");
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的流式响应体
+    ///
+    /// 验证：
+    /// - 大文件以流的形式完整转发，内容与存储一致
+    /// - 对象不存在时返回 404（由调用方决定是否回退）
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_streams_body() {
+        let content: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        let mut mock_storage = MockStorage::new();
+        let chunks: Vec<Bytes> = content.chunks(64 * 1024).map(Bytes::copy_from_slice).collect();
+        let size = content.len() as u64;
         mock_storage
-            .expect_get_presigned_url()
-            .returning(move |_| Ok(format!("{}/test.txt", mock_uri)));
-        
-        let http_client = reqwest::Client::new();
-        
+            .expect_get_object()
+            .with(eq("www/video.mp4"), always())
+            .returning(move |_, _| {
+                Ok(GetObjectOutput {
+                    status: StatusCode::OK,
+                    meta: ObjectMeta {
+                        content_length: size,
+                        ..ObjectMeta::default()
+                    },
+                    range: None,
+                    body: stream::iter(chunks.clone().into_iter().map(Ok)).boxed(),
+                })
+            });
+        mock_storage
+            .expect_get_object()
+            .with(eq("www/missing.mp4"), always())
+            .returning(|_, _| Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND)));
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &HeaderMap::new(),
+            "www/video.mp4",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            response.headers().get(http::header::CONTENT_LENGTH).unwrap(),
+            &content.len().to_string()
+        );
+        assert_eq!(response.headers()["content-type"], "video/mp4");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), content.as_slice());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &HeaderMap::new(),
+            "www/missing.mp4",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    /// 构造 Range 测试使用的对象元数据
+    fn range_meta(size: u64) -> ObjectMeta {
+        ObjectMeta {
+            content_length: size,
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            content_type: Some("text/plain".to_string()),
+            ..ObjectMeta::default()
+        }
+    }
+
+    /// 以 `range_meta` 为元数据、按请求的区间返回 `Hello World` 的存储
+    fn range_storage(mut mock_storage: MockStorage) -> Arc<dyn Storage> {
+        mock_storage
+            .expect_head()
+            .returning(|_| Ok(Some(range_meta(11))));
+        mock_storage
+            .expect_get_object()
+            .returning(|_, options| {
+                Ok(match options.range {
+                    Some(range) => GetObjectOutput::partial(range_meta(11), range, b"Hello World"),
+                    None => GetObjectOutput::full(range_meta(11), "Hello World"),
+                })
+            });
+        Arc::new(mock_storage)
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的单区间请求
+    ///
+    /// 验证：
+    /// - 区间按对象大小校验后传给存储，并以元数据的 ETag 作为 If-Match
+    /// - 返回 206 和 Content-Range 头
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_single_range() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_get_object()
+            .with(
+                eq("www/test.txt"),
+                eq(GetObjectOptions {
+                    range: Some((6, 10)),
+                    if_match: Some("\"v1\"".to_string()),
+                    ..GetObjectOptions::default()
+                }),
+            )
+            .times(1)
+            .returning(|_, _| Ok(GetObjectOutput::partial(range_meta(11), (6, 10), b"Hello World")));
+        let storage = range_storage(mock_storage);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("range", "bytes=6-".parse().unwrap());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &headers,
+            "www/test.txt",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()["content-range"], "bytes 6-10/11");
+        assert_eq!(response.headers()["content-length"], "5");
+        assert_eq!(response.headers()["accept-ranges"], "bytes");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), b"World");
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的多区间请求
+    ///
+    /// 验证：
+    /// - 返回 206 和 multipart/byteranges 响应体
+    /// - 每个分段包含正确的 Content-Range 和内容
+    /// - Content-Length 与实际响应体长度一致
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_multiple_ranges() {
+        let storage = range_storage(MockStorage::new());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("range", "bytes=6-10,0-4".parse().unwrap());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &headers,
+            "www/test.txt",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = response.headers()["content-type"].to_str().unwrap().to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+        let content_length: usize = response.headers()["content-length"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(body.len(), content_length);
+        assert_eq!(
+            body,
+            format!(
+                "\r\n--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-4/11\r\n\r\nHello\
+                 \r\n--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 6-10/11\r\n\r\nWorld\
+                 \r\n--{boundary}--\r\n"
+            )
+        );
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的无法满足的区间
+    ///
+    /// 验证：
+    /// - 区间起始位置超出对象大小时返回 416
+    /// - Content-Range 头包含对象大小
+    /// - 不会读取对象内容
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_range_not_satisfiable() {
+        let mut mock_storage = MockStorage::new();
+
+        mock_storage
+            .expect_head()
+            .returning(|_| Ok(Some(range_meta(11))));
+        mock_storage.expect_get_object().never();
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("range", "bytes=100-200".parse().unwrap());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &headers,
+            "www/test.txt",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()["content-range"], "bytes */11");
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的 If-Range 不匹配
+    ///
+    /// 验证：
+    /// - If-Range 与对象 ETag 不匹配时忽略 Range 头
+    /// - 返回 200 和完整内容，且不向存储请求区间
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_if_range_mismatch() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_get_object()
+            .withf(|_, options| options.range.is_none())
+            .times(1)
+            .returning(|_, _| Ok(GetObjectOutput::full(range_meta(11), "Hello World")));
+        let storage = range_storage(mock_storage);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("range", "bytes=0-4".parse().unwrap());
+        headers.insert("if-range", "\"v0\"".parse().unwrap());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &headers,
+            "www/test.txt",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("content-range"));
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的 If-None-Match 命中
+    ///
+    /// 验证：
+    /// - ETag 匹配时返回 304，并带上 ETag 和缓存头
+    /// - 不会读取对象内容
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_not_modified() {
+        let mut mock_storage = MockStorage::new();
+
+        mock_storage
+            .expect_head()
+            .with(eq("www/app.js"))
+            .returning(|_| Ok(Some(range_meta(11))));
+        mock_storage.expect_get_object().never();
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("if-none-match", "\"v1\"".parse().unwrap());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &headers,
+            "www/app.js",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], "\"v1\"");
+        assert!(response.headers().contains_key("cache-control"));
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的 If-Match 不匹配
+    ///
+    /// 验证：
+    /// - ETag 不匹配时返回 412
+    /// - 不会读取对象内容
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_precondition_failed() {
+        let mut mock_storage = MockStorage::new();
+
+        mock_storage
+            .expect_head()
+            .returning(|_| Ok(Some(range_meta(11))));
+        mock_storage.expect_get_object().never();
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("if-match", "\"v0\"".parse().unwrap());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &headers,
+            "www/test.txt",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    }
+
+    /// 测试 fetch_and_proxy_file 函数的 If-None-Match 未命中
+    ///
+    /// 验证：
+    /// - ETag 不匹配时返回 200 和完整内容
+    /// - 已在本地评估的条件不会传给存储，读取时只带元数据的 ETag 作为 If-Match
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_modified() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_get_object()
+            .with(
+                eq("www/test.txt"),
+                eq(GetObjectOptions {
+                    if_match: Some("\"v1\"".to_string()),
+                    ..GetObjectOptions::default()
+                }),
+            )
+            .times(1)
+            .returning(|_, _| Ok(GetObjectOutput::full(range_meta(11), "Hello World")));
+        let storage = range_storage(mock_storage);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("if-none-match", "\"v0\"".parse().unwrap());
+
+        let response = fetch_and_proxy_file(
+            &storage,
+            &headers,
+            "www/test.txt",
+            &SiteConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["etag"], "\"v1\"");
+    }
+
+    /// 测试 fetch_and_proxy_file 函数在对象被修改时的处理
+    ///
+    /// 验证：
+    /// - 获取元数据之后对象被修改（读取时 If-Match 不满足）时返回错误，
+    ///   而不是返回与元数据不一致的内容
+    #[tokio::test]
+    async fn test_fetch_and_proxy_file_object_changed() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_head()
+            .returning(|_| Ok(Some(range_meta(11))));
+        mock_storage
+            .expect_get_object()
+            .returning(|_, _| Ok(GetObjectOutput::empty(StatusCode::PRECONDITION_FAILED)));
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("range", "bytes=0-4".parse().unwrap());
+
         let result = fetch_and_proxy_file(
-            &mock_storage,
-            &http_client,
-            &http::HeaderMap::new(),
+            &storage,
+            &headers,
             "www/test.txt",
+            &SiteConfig::default(),
         )
         .await;
 
-        assert!(result.is_ok());
-        let response = result.unwrap();
-        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(matches!(result, Err(crate::error::AppError::S3(_))));
     }
 
     /// 测试 should_cache 函数的缓存判断逻辑
@@ -624,11 +1375,120 @@ mod tests {
     /// - HTML 文件（.html, .htm）不应该被缓存（返回 false）
     #[test]
     fn test_should_cache() {
-        assert!(should_cache("file.css"));
-        assert!(should_cache("file.js"));
-        assert!(should_cache("image.png"));
-        assert!(!should_cache("page.html"));
-        assert!(!should_cache("page.htm"));
+        assert!(should_cache("file.css", NO_CACHE_EXTS));
+        assert!(should_cache("file.js", NO_CACHE_EXTS));
+        assert!(should_cache("image.png", NO_CACHE_EXTS));
+        assert!(!should_cache("page.html", NO_CACHE_EXTS));
+        assert!(!should_cache("page.htm", NO_CACHE_EXTS));
+    }
+
+    /// 测试 cache_control 函数的缓存头计算
+    ///
+    /// 验证：
+    /// - 默认情况下 HTML 返回 `no-cache`，其余文件返回 `public, max-age=..., must-revalidate`
+    /// - 缓存规则按去掉站点前缀后的请求路径匹配，优先于按扩展名的默认策略
+    /// - 开启 `trust_object_cache_control` 时对象自身的 Cache-Control 优先于规则，未开启时忽略
+    #[test]
+    fn test_cache_control() {
+        let site = SiteConfig::default();
+        let meta = ObjectMeta::default();
+        assert_eq!(cache_control(&site, "www/index.html", &meta), "no-cache");
+        assert_eq!(
+            cache_control(&site, "www/app.js", &meta),
+            "public, max-age=3600, must-revalidate"
+        );
+
+        let mut site: SiteConfig = toml::from_str(
+            r#"
+            [[cache.rules]]
+            glob = "assets/*"
+            cache_control = "public, max-age=31536000, immutable"
+
+            [[cache.rules]]
+            regex = "\\.html$"
+            cache_control = "no-store"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            cache_control(&site, "www/assets/app.js", &meta),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(cache_control(&site, "www/index.html", &meta), "no-store");
+        assert_eq!(
+            cache_control(&site, "www/www/assets/app.js", &meta),
+            "public, max-age=3600, must-revalidate"
+        );
+
+        let object = ObjectMeta {
+            cache_control: Some("max-age=60".to_string()),
+            ..ObjectMeta::default()
+        };
+        assert_eq!(
+            cache_control(&site, "www/assets/app.js", &object),
+            "public, max-age=31536000, immutable"
+        );
+        site.cache.trust_object_cache_control = true;
+        assert_eq!(cache_control(&site, "www/assets/app.js", &object), "max-age=60");
+        assert_eq!(
+            cache_control(&site, "www/assets/app.js", &meta),
+            "public, max-age=31536000, immutable"
+        );
+    }
+
+    /// 测试内容哈希文件名的识别
+    ///
+    /// 验证：
+    /// - 常见打包工具（Vite、webpack、Parcel、esbuild）输出的哈希文件名返回 immutable 长期缓存
+    /// - `favicon.ico`、`app.minified.js` 等普通文件名返回较短的重新验证策略
+    /// - HTML 即使带哈希也返回 `no-cache`
+    /// - 可以关闭或替换匹配模式
+    #[test]
+    fn test_hashed_filenames() {
+        let site = SiteConfig::default();
+        let meta = ObjectMeta::default();
+
+        for key in [
+            "www/assets/index-3f9a1c2b.js",
+            "www/assets/index-BxA1_c9Z.js",
+            "www/assets/index-Dk-2fQ9a.css",
+            "www/assets/logo-aBcDeFgH.svg",
+            "www/static/js/main.3f9a1c2b.chunk.js",
+            "www/static/js/main.3f9a1c2b.js.map",
+            "www/index.8d7f6e5c4b3a2910.css",
+            "www/chunk-7XKQ2ZPD.js",
+        ] {
+            assert!(site.cache.is_hashed(key), "{key}");
+            assert_eq!(cache_control(&site, key, &meta), IMMUTABLE_CACHE_CONTROL, "{key}");
+        }
+        for key in [
+            "www/favicon.ico",
+            "www/app.minified.js",
+            "www/icons/icon-Settings.svg",
+            "www/robots.txt",
+            "www/jquery-3.7.1.min.js",
+            "www/assets/3f9a1c2b/app.js",
+        ] {
+            assert!(!site.cache.is_hashed(key), "{key}");
+            assert_eq!(
+                cache_control(&site, key, &meta),
+                "public, max-age=3600, must-revalidate",
+                "{key}"
+            );
+        }
+        assert_eq!(cache_control(&site, "www/index-3f9a1c2b.html", &meta), "no-cache");
+
+        let mut site: SiteConfig = toml::from_str(
+            r#"
+            [cache]
+            hashed_pattern = '\.v\d+\.'
+            "#,
+        )
+        .unwrap();
+        assert!(site.cache.is_hashed("www/app.v12.js"));
+        assert!(!site.cache.is_hashed("www/index-3f9a1c2b.js"));
+        site.cache.immutable_hashed = false;
+        assert!(!site.cache.is_hashed("www/app.v12.js"));
     }
 
     /// 测试 find_exists_key 函数 - 直接目录索引存在
@@ -645,7 +1505,7 @@ mod tests {
             .with(eq("www/app/page/index.html"))
             .returning(|_| Ok(true));
         
-        let result = find_exists_key(&mock_storage, "app/page").await;
+        let result = find_exists_key(&mock_storage, &SiteConfig::default(), "app/page").await;
         
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some("www/app/page/index.html".to_string()));
@@ -672,7 +1532,7 @@ mod tests {
             .with(eq("www/app/index.html"))
             .returning(|_| Ok(true));
         
-        let result = find_exists_key(&mock_storage, "app/page").await;
+        let result = find_exists_key(&mock_storage, &SiteConfig::default(), "app/page").await;
         
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some("www/app/index.html".to_string()));
@@ -705,7 +1565,7 @@ mod tests {
             .with(eq("www/index.html"))
             .returning(|_| Ok(true));
         
-        let result = find_exists_key(&mock_storage, "app/page").await;
+        let result = find_exists_key(&mock_storage, &SiteConfig::default(), "app/page").await;
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some("www/index.html".to_string()));
@@ -736,7 +1596,7 @@ mod tests {
             .with(eq("www/index.html"))
             .returning(|_| Ok(false));
         
-        let result = find_exists_key(&mock_storage, "app/page").await;
+        let result = find_exists_key(&mock_storage, &SiteConfig::default(), "app/page").await;
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), None);
@@ -761,12 +1621,46 @@ mod tests {
             .with(eq("www/index.html"))
             .returning(|_| Ok(true));
         
-        let result = find_exists_key(&mock_storage, "app").await;
+        let result = find_exists_key(&mock_storage, &SiteConfig::default(), "app").await;
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some("www/index.html".to_string()));
     }
 
+    /// 测试 find_exists_key 函数 - 空前缀和多个索引文件候选
+    ///
+    /// 验证：
+    /// - 前缀为空时存储键不带前缀
+    /// - 每一级目录按配置顺序尝试所有索引文件后才向上查找
+    #[tokio::test]
+    async fn test_find_exists_key_index_candidates() {
+        let mut mock_storage = MockStorage::new();
+        let mut seq = mockall::Sequence::new();
+        for key in ["docs/index.html", "docs/index.htm", "index.html"] {
+            mock_storage
+                .expect_check_key_exists()
+                .with(eq(key))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_| Ok(false));
+        }
+        mock_storage
+            .expect_check_key_exists()
+            .with(eq("index.htm"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(true));
+
+        let site = SiteConfig {
+            prefix: String::new(),
+            index_files: vec!["index.html".to_string(), "index.htm".to_string()],
+            ..SiteConfig::default()
+        };
+        let result = find_exists_key(&mock_storage, &site, "docs").await;
+
+        assert_eq!(result.unwrap(), Some("index.htm".to_string()));
+    }
+
     /// 测试 handle_files 函数 - 成功获取文件
     ///
     /// 验证：
@@ -774,27 +1668,15 @@ mod tests {
     /// - 返回正确的文件内容
     #[tokio::test]
     async fn test_handle_files_success() {
-        let mock_server = MockServer::start().await;
         let mut mock_storage = MockStorage::new();
-        let mock_uri = mock_server.uri();
 
-        // Mock 存储返回预签名 URL
+        // Mock 存储返回文件内容
         mock_storage
-            .expect_get_presigned_url()
-            .with(eq("www/test.txt"))
-            .returning(move |_| Ok(format!("{}/test.txt", mock_uri)));
-        
-        // Wiremock 模拟 S3 返回文件内容
-        Mock::given(method("GET"))
-            .and(path("/test.txt"))
-            .respond_with(ResponseTemplate::new(200).set_body_string("Hello World"))
-            .mount(&mock_server)
-            .await;
-        
-        let state = AppState {
-            storage: Arc::new(mock_storage),
-            http_client: reqwest::Client::new(),
-        };
+            .expect_get_object()
+            .with(eq("www/test.txt"), always())
+            .returning(|_, _| Ok(GetObjectOutput::full(ObjectMeta::default(), "Hello World")));
+
+        let state = AppState::new(Arc::new(mock_storage), reqwest::Client::new());
 
         let req = Request::builder()
             .uri("/test.txt")
@@ -818,9 +1700,9 @@ mod tests {
 
         // 文件不存在
         mock_storage
-            .expect_get_presigned_url()
-            .with(eq("www/missing.txt"))
-            .returning(|_| Err(crate::error::AppError::NotFound));
+            .expect_get_object()
+            .with(eq("www/missing.txt"), always())
+            .returning(|_, _| Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND)));
         
         // SPA fallback 也找不到
         mock_storage
@@ -833,13 +1715,11 @@ mod tests {
             .with(eq("www/index.html"))
             .returning(|_| Ok(false));
         
-        let state = AppState {
-            storage: Arc::new(mock_storage),
-            http_client: reqwest::Client::new(),
-        };
+        let state = AppState::new(Arc::new(mock_storage), reqwest::Client::new());
 
         let req = Request::builder()
             .uri("/missing.txt")
+            .header("accept", "text/html")
             .body(Body::empty())
             .unwrap();
 
@@ -851,18 +1731,232 @@ mod tests {
         }
     }
 
-    /// 测试 handle_files 函数 - 空路径返回 404
+    /// 测试 HEAD 请求只读取对象元数据
+    ///
+    /// 验证：
+    /// - 不调用 `get_object`，不读取对象内容
+    /// - 返回与 GET 相同的 Content-Length、Content-Type、ETag、Last-Modified 和缓存头
+    /// - 忽略 Range 头，返回 200 和完整大小
+    /// - 文件不存在时返回 404，不读取自定义错误页面
+    #[tokio::test]
+    async fn test_handle_files_head() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage.expect_get_object().never();
+        mock_storage
+            .expect_head()
+            .with(eq("www/app.js"))
+            .returning(|_| {
+                Ok(Some(ObjectMeta {
+                    content_length: 1234,
+                    etag: Some("\"abc\"".to_string()),
+                    last_modified: Some(
+                        std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+                    ),
+                    ..ObjectMeta::default()
+                }))
+            });
+        mock_storage.expect_head().returning(|_| Ok(None));
+        mock_storage
+            .expect_check_key_exists()
+            .returning(|_| Ok(false));
+        let state = AppState::new(Arc::new(mock_storage), reqwest::Client::new());
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        for resp in [
+            server.method(Method::HEAD, "/app.js").await,
+            server
+                .method(Method::HEAD, "/app.js")
+                .add_header(RANGE, "bytes=0-9")
+                .await,
+        ] {
+            resp.assert_status(StatusCode::OK);
+            assert_eq!(resp.header(CONTENT_LENGTH), "1234");
+            assert_eq!(resp.header(CONTENT_TYPE), "text/javascript");
+            assert_eq!(resp.header(ETAG), "\"abc\"");
+            assert_eq!(resp.header(LAST_MODIFIED), "Tue, 14 Nov 2023 22:13:20 GMT");
+            assert_eq!(resp.header(CACHE_CONTROL), "public, max-age=3600, must-revalidate");
+            assert!(resp.as_bytes().is_empty());
+        }
+
+        server
+            .method(Method::HEAD, "/missing.js")
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    /// 测试不支持的请求方法
     ///
     /// 验证：
-    /// - 当请求路径为空时返回 404
+    /// - OPTIONS 返回 204 和 `Allow` 头
+    /// - POST、PUT、DELETE 返回 405 和 `Allow` 头，不访问存储
+    #[tokio::test]
+    async fn test_handle_files_methods() {
+        let state = AppState::new(Arc::new(MockStorage::new()), reqwest::Client::new());
+
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+        let resp = server.method(Method::OPTIONS, "/app.js").await;
+        resp.assert_status(StatusCode::NO_CONTENT);
+        assert_eq!(resp.header(ALLOW), "GET, HEAD, OPTIONS");
+
+        for method in [Method::POST, Method::PUT, Method::DELETE] {
+            let resp = server.method(method.clone(), "/app.js").await;
+            resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+            assert_eq!(resp.header(ALLOW), "GET, HEAD, OPTIONS", "{method}");
+        }
+    }
+
+    /// 测试请求路径的规范化
+    ///
+    /// 验证：
+    /// - 百分号编码的文件名、重复斜杠和 `.` 段映射到同一个对象
+    /// - 越过站点根目录的 `..`（包括编码形式）和 NUL 返回 400，不会读取前缀之外的文件
+    /// - 配置为小写时大小写不同的路径映射到小写的对象
+    #[tokio::test]
+    async fn test_handle_files_normalizes_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("site/docs")).unwrap();
+        std::fs::write(dir.path().join("site/docs/hello world.txt"), "hello").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        let storage = || {
+            Arc::new(crate::storage::FsStorage::new(dir.path().join("site"), "www").unwrap())
+        };
+
+        let state = AppState::new(storage(), reqwest::Client::new());
+        let server = axum_test::TestServer::new(crate::router(state.clone(), Vec::new())).unwrap();
+        for uri in [
+            "/docs/hello%20world.txt",
+            "//docs//./hello%20world.txt",
+            "/docs%2Fhello%20world.txt",
+        ] {
+            let resp = server.get(uri).await;
+            resp.assert_status(StatusCode::OK);
+            assert_eq!(resp.text(), "hello", "{uri}");
+        }
+        // HTTP 客户端会解析 URL 中的 `..`，直接构造请求以保留原始路径
+        for uri in [
+            "/../secret.txt",
+            "/..%2Fsecret.txt",
+            "/docs/%2e%2e/%2e%2e/secret.txt",
+            "/docs/a%00.txt",
+        ] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let result = handle_files(axum::extract::State(state.clone()), req).await;
+            assert!(
+                matches!(result, Err(crate::error::AppError::InvalidPath(_))),
+                "{uri}"
+            );
+        }
+
+        let site = SiteConfig {
+            path_case: crate::config::PathCase::Lowercase,
+            ..SiteConfig::default()
+        };
+        let state = AppState::with_site(storage(), reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+        let resp = server.get("/Docs/Hello%20World.TXT").await;
+        resp.assert_status(StatusCode::OK);
+        assert_eq!(resp.text(), "hello");
+    }
+
+    /// 测试 handle_files 函数 - 站点根目录返回索引文件
+    ///
+    /// 验证：
+    /// - 请求 `/` 时直接查找根目录的 index.html，不请求空路径对应的对象
+    /// - 返回 index.html 的内容
+    #[tokio::test]
+    async fn test_handle_files_root_path() {
+        let mut mock_storage = MockStorage::new();
+
+        mock_storage
+            .expect_check_key_exists()
+            .with(eq("www/index.html"))
+            .times(1)
+            .returning(|_| Ok(true));
+        mock_storage
+            .expect_get_object()
+            .with(eq("www/index.html"), always())
+            .times(1)
+            .returning(|_, _| Ok(GetObjectOutput::full(ObjectMeta::default(), "<html></html>")));
+
+        let state = AppState::new(Arc::new(mock_storage), reqwest::Client::new());
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let resp = handle_files(axum::extract::State(state), req)
+            .await
+            .unwrap()
+            .into_response();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "<html></html>");
+    }
+
+    /// 测试 handle_files 函数 - 按 Host 头选择站点
+    ///
+    /// 验证：
+    /// - 匹配虚拟主机时使用该站点的存储和前缀
+    /// - 未匹配的 Host 使用默认站点
+    #[tokio::test]
+    async fn test_handle_files_virtual_host() {
+        let object_storage = |key: &'static str| {
+            let mut mock_storage = MockStorage::new();
+            mock_storage
+                .expect_get_object()
+                .with(eq(key), always())
+                .times(1)
+                .returning(|_, _| {
+                    Ok(GetObjectOutput::full(ObjectMeta::default(), "console.log(1)"))
+                });
+            Arc::new(mock_storage)
+        };
+
+        let mut hosts = VirtualHosts::new(Site::new(
+            object_storage("www/app.js"),
+            SiteConfig::default(),
+        ));
+        hosts.add(
+            "*.preview.example.com",
+            Site::new(
+                object_storage("previews/app.js"),
+                SiteConfig {
+                    prefix: "previews".to_string(),
+                    ..SiteConfig::default()
+                },
+            ),
+        );
+        let state = AppState {
+            hosts: Arc::new(hosts),
+            http_client: reqwest::Client::new(),
+        };
+
+        for host in ["pr-1.preview.example.com:8080", "example.com"] {
+            let req = Request::builder()
+                .uri("/app.js")
+                .header("host", host)
+                .body(Body::empty())
+                .unwrap();
+            let resp = handle_files(axum::extract::State(state.clone()), req)
+                .await
+                .unwrap()
+                .into_response();
+            assert_eq!(resp.status(), StatusCode::OK, "{host}");
+        }
+    }
+
+    /// 测试 handle_files 函数 - 关闭 serve_root 时空路径返回 404
+    ///
+    /// 验证：
+    /// - 当请求路径为空且 serve_root 为 false 时返回 404
+    /// - 不访问存储后端
     #[tokio::test]
     async fn test_handle_files_empty_path() {
         let mock_storage = MockStorage::new();
         
-        let state = AppState {
-            storage: Arc::new(mock_storage),
-            http_client: reqwest::Client::new(),
+        let site = SiteConfig {
+            serve_root: false,
+            ..SiteConfig::default()
         };
+        let state = AppState::with_site(Arc::new(mock_storage), reqwest::Client::new(), site);
         
         let req = Request::builder()
             .uri("/")
@@ -884,25 +1978,14 @@ mod tests {
     /// - 最终返回找到的 index.html
     #[tokio::test]
     async fn test_handle_files_spa_fallback() {
-        let mock_server = MockServer::start().await;
         let mut mock_storage = MockStorage::new();
-        let mock_uri = mock_server.uri();
 
         // 第一次请求返回 404
         mock_storage
-            .expect_get_presigned_url()
-            .with(eq("www/app/page"))
-            .returning({
-                let uri = mock_uri.clone();
-                move |_| Ok(format!("{}/app/page", uri))
-            });
-        
-        Mock::given(method("GET"))
-            .and(path("/app/page"))
-            .respond_with(ResponseTemplate::new(404))
-            .mount(&mock_server)
-            .await;
-        
+            .expect_get_object()
+            .with(eq("www/app/page"), always())
+            .returning(|_, _| Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND)));
+
         // SPA fallback 找到 index.html
         mock_storage
             .expect_check_key_exists()
@@ -915,20 +1998,11 @@ mod tests {
             .returning(|_| Ok(true));
         
         mock_storage
-            .expect_get_presigned_url()
-            .with(eq("www/app/index.html"))
-            .returning(move |_| Ok(format!("{}/app/index.html", mock_uri)));
-        
-        Mock::given(method("GET"))
-            .and(path("/app/index.html"))
-            .respond_with(ResponseTemplate::new(200).set_body_string("SPA App"))
-            .mount(&mock_server)
-            .await;
-        
-        let state = AppState {
-            storage: Arc::new(mock_storage),
-            http_client: reqwest::Client::new(),
-        };
+            .expect_get_object()
+            .with(eq("www/app/index.html"), always())
+            .returning(|_, _| Ok(GetObjectOutput::full(ObjectMeta::default(), "SPA App")));
+
+        let state = AppState::new(Arc::new(mock_storage), reqwest::Client::new());
 
         let req = Request::builder()
             .uri("/app/page")
@@ -940,5 +2014,69 @@ mod tests {
         assert!(response.is_ok());
         let resp = response.unwrap().into_response();
         assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "SPA App");
+    }
+
+    /// 测试 handle_files 函数 - SPA 回退策略
+    ///
+    /// 验证：
+    /// - 缺失的静态资源（`.js`）不回退，即使 Accept 包含 text/html
+    /// - 带扩展名且不接受 HTML 的请求不回退，只查找目录索引
+    /// - 回退状态码配置为 404 时返回索引文件内容和 404
+    /// - 关闭回退时无扩展名的路径也直接返回 404
+    #[tokio::test]
+    async fn test_handle_files_fallback_policy() {
+        let storage = |fallback: bool| {
+            let mut mock_storage = MockStorage::new();
+            mock_storage
+                .expect_get_object()
+                .with(eq("www/index.html"), always())
+                .times(usize::from(fallback))
+                .returning(|_, _| Ok(GetObjectOutput::full(ObjectMeta::default(), "SPA App")));
+            mock_storage
+                .expect_get_object()
+                .returning(|_, _| Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND)));
+            mock_storage
+                .expect_check_key_exists()
+                .with(eq("www/index.html"))
+                .times(usize::from(fallback))
+                .returning(|_| Ok(true));
+            mock_storage
+                .expect_check_key_exists()
+                .returning(|_| Ok(false));
+            Arc::new(mock_storage)
+        };
+        let request = |uri: &str, accept: &str| {
+            Request::builder()
+                .uri(uri)
+                .header("accept", accept)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for (uri, accept) in [("/assets/app.js", "text/html"), ("/data/report.csv", "*/*")] {
+            let state = AppState::new(storage(false), reqwest::Client::new());
+            let result = handle_files(axum::extract::State(state), request(uri, accept)).await;
+            assert!(matches!(result, Err(crate::error::AppError::NotFound)), "{uri}");
+        }
+
+        let mut site = SiteConfig::default();
+        site.fallback.status = 404;
+        let state = AppState::with_site(storage(true), reqwest::Client::new(), site);
+        let resp = handle_files(axum::extract::State(state), request("/app/page", "*/*"))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "SPA App");
+
+        let mut site = SiteConfig::default();
+        site.fallback.enabled = false;
+        let state = AppState::with_site(storage(false), reqwest::Client::new(), site);
+        let req = request("/app/page", "text/html");
+        let result = handle_files(axum::extract::State(state), req).await;
+        assert!(matches!(result, Err(crate::error::AppError::NotFound)));
     }
 }
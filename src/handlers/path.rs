@@ -0,0 +1,173 @@
+//! 请求路径规范化
+//!
+//! 请求路径在映射为存储键之前统一规范化：百分号解码、去掉空段和 `.`、解析 `..`，
+//! 并拒绝控制字符、反斜杠和越过站点根目录的 `..`，保证存储键不会逃出配置的前缀
+//! （无论是 S3 还是本地目录存储）。
+
+use crate::config::PathCase;
+use crate::error::AppError;
+use percent_encoding::percent_decode_str;
+
+/// 规范化请求路径，返回不含开头和末尾 `/` 的相对路径，站点根目录为空字符串
+///
+/// 只解码一次：`%252e` 解码为字面量 `%2e`，不会再被当作 `.`。
+/// 解码后的 `%2F` 与 `/` 同样作为分隔符。
+///
+/// # 错误
+///
+/// 以下情况返回 `AppError::InvalidPath`（400）：
+/// - 解码后不是合法的 UTF-8
+/// - 包含控制字符（包括 NUL）或反斜杠
+/// - `..` 越过站点根目录
+pub fn normalize_path(raw: &str, case: PathCase) -> Result<String, AppError> {
+    let decoded = percent_decode_str(raw)
+        .decode_utf8()
+        .map_err(|_| AppError::InvalidPath("not valid UTF-8".to_string()))?;
+    if decoded.chars().any(char::is_control) {
+        return Err(AppError::InvalidPath("control character".to_string()));
+    }
+    if decoded.contains('\\') {
+        return Err(AppError::InvalidPath("backslash".to_string()));
+    }
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(AppError::InvalidPath("escapes the site root".to_string()));
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let path = segments.join("/");
+    Ok(match case {
+        PathCase::Preserve => path,
+        PathCase::Lowercase => path.to_lowercase(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+    use proptest::prelude::*;
+
+    fn normalize(raw: &str) -> Result<String, AppError> {
+        normalize_path(raw, PathCase::Preserve)
+    }
+
+    /// 测试常见路径的规范化
+    ///
+    /// 验证：
+    /// - 去掉开头、末尾和重复的 `/`，去掉 `.` 段
+    /// - 站点根目录内的 `..` 被解析
+    /// - 百分号编码只解码一次，`%2F` 作为分隔符
+    /// - 配置为小写时路径被转为小写
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize("/").unwrap(), "");
+        assert_eq!(normalize("").unwrap(), "");
+        assert_eq!(normalize("/app.js").unwrap(), "app.js");
+        assert_eq!(normalize("//docs///guide/").unwrap(), "docs/guide");
+        assert_eq!(normalize("/./docs/./index.html").unwrap(), "docs/index.html");
+        assert_eq!(normalize("/docs/../app.js").unwrap(), "app.js");
+        assert_eq!(normalize("/hello%20world.txt").unwrap(), "hello world.txt");
+        assert_eq!(normalize("/%E6%96%87%E6%A1%A3.pdf").unwrap(), "文档.pdf");
+        assert_eq!(normalize("/docs%2Fguide").unwrap(), "docs/guide");
+        assert_eq!(normalize("/a/%2e%2e/b").unwrap(), "b");
+        assert_eq!(normalize("/%252e%252e/b").unwrap(), "%2e%2e/b");
+        assert_eq!(
+            normalize_path("/Docs/README.MD", PathCase::Lowercase).unwrap(),
+            "docs/readme.md"
+        );
+    }
+
+    /// 测试拒绝的路径
+    ///
+    /// 验证：
+    /// - 越过站点根目录的 `..`（包括编码形式）返回 400
+    /// - NUL、换行等控制字符和反斜杠返回 400
+    /// - 解码后不是合法 UTF-8 的路径返回 400
+    #[test]
+    fn test_normalize_path_rejected() {
+        for raw in [
+            "/..",
+            "/../etc/passwd",
+            "/docs/../../etc/passwd",
+            "/%2e%2e/etc/passwd",
+            "/..%2F..%2Fetc%2Fpasswd",
+            "/app.js%00.png",
+            "/app\n.js",
+            "/a%0d%0ab",
+            "/%7F",
+            "/..%5C..%5Cwindows",
+            "/a\\b",
+            "/%FF%FE",
+        ] {
+            let error = normalize(raw).unwrap_err();
+            assert!(matches!(error, AppError::InvalidPath(_)), "{raw}");
+            assert_eq!(error.status_code(), axum::http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    /// 容易触发路径问题的片段，与任意字符串混合生成请求路径
+    fn path_strategy() -> impl Strategy<Value = String> {
+        let piece = prop_oneof![
+            Just("/".to_string()),
+            Just("..".to_string()),
+            Just(".".to_string()),
+            Just("%2e".to_string()),
+            Just("%2E%2e".to_string()),
+            Just("%2f".to_string()),
+            Just("%5c".to_string()),
+            Just("%00".to_string()),
+            Just("%25".to_string()),
+            Just("\\".to_string()),
+            "[a-zA-Z0-9._~-]{1,8}",
+            any::<String>(),
+        ];
+        prop::collection::vec(piece, 0..16).prop_map(|pieces| pieces.concat())
+    }
+
+    proptest! {
+        /// 规范化成功时，结果不含可逃出前缀的段，映射到的存储键总在前缀之内
+        #[test]
+        fn prop_normalized_path_stays_in_prefix(raw in path_strategy()) {
+            if let Ok(path) = normalize(&raw) {
+                prop_assert!(!path.starts_with('/') && !path.ends_with('/'));
+                prop_assert!(!path.contains('\\'));
+                prop_assert!(!path.chars().any(char::is_control));
+                for segment in path.split('/').filter(|_| !path.is_empty()) {
+                    prop_assert!(!matches!(segment, "" | "." | ".."), "{path}");
+                }
+                let key = crate::config::SiteConfig::default().object_key(&path);
+                prop_assert!(key.starts_with("www/"));
+            }
+        }
+
+        /// 规范化结果重新百分号编码后再次规范化，得到相同的路径
+        #[test]
+        fn prop_normalize_path_round_trip(raw in path_strategy()) {
+            if let Ok(path) = normalize(&raw) {
+                let encoded: String = path
+                    .split('/')
+                    .map(|segment| utf8_percent_encode(segment, NON_ALPHANUMERIC).to_string())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                prop_assert_eq!(normalize(&format!("/{encoded}")).unwrap(), path);
+            }
+        }
+
+        /// 任意输入都不会 panic，且小写模式的结果是保留大小写结果的小写形式
+        #[test]
+        fn prop_lowercase_matches_preserve(raw in any::<String>()) {
+            let preserve = normalize(&raw).ok();
+            let lowercase = normalize_path(&raw, PathCase::Lowercase).ok();
+            prop_assert_eq!(preserve.map(|p| p.to_lowercase()), lowercase);
+        }
+    }
+}
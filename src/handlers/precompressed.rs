@@ -0,0 +1,287 @@
+//! 预压缩文件
+//!
+//! 构建流程会把 `app.js.br`、`app.js.gz` 与 `app.js` 一起上传。客户端接受对应编码时，
+//! 优先返回这些预压缩的同名文件，省去实时压缩的开销并获得更高的压缩率。
+
+use crate::compression::is_compressible_type;
+use crate::config::SiteConfig;
+use crate::error::AppError;
+use crate::handlers::files::{fetch_file_as, guess_content_type};
+use crate::storage::Storage;
+use axum::body::Body;
+use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, VARY};
+use axum::http::{HeaderMap, HeaderValue, Method, Response, StatusCode};
+use std::sync::Arc;
+
+/// 支持的预压缩编码：（Content-Encoding，文件扩展名），权重相同时按此顺序优先
+pub const PRECOMPRESSED_ENCODINGS: &[(&str, &str)] = &[("br", "br"), ("gzip", "gz")];
+
+/// 解析 Accept-Encoding，返回客户端接受的预压缩编码（按权重从高到低排序）
+///
+/// 未明确列出的编码使用 `*` 的权重，`q=0` 表示不接受。`x-gzip` 视为 `gzip`。
+pub fn accepted_encodings(headers: &HeaderMap) -> Vec<(&'static str, &'static str)> {
+    let mut weights: Vec<(&str, f32)> = Vec::new();
+    for item in headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+    {
+        let mut params = item.split(';');
+        let coding = params.next().unwrap_or_default().trim();
+        let q = params
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        let coding = if coding.eq_ignore_ascii_case("x-gzip") {
+            "gzip"
+        } else {
+            coding
+        };
+        weights.push((coding, q));
+    }
+
+    let weight_of = |coding: &str| {
+        weights
+            .iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(coding))
+            .or_else(|| weights.iter().find(|(c, _)| *c == "*"))
+            .map_or(0.0, |(_, q)| *q)
+    };
+
+    let mut accepted: Vec<_> = PRECOMPRESSED_ENCODINGS
+        .iter()
+        .map(|&(encoding, ext)| (encoding, ext, weight_of(encoding)))
+        .filter(|(_, _, q)| *q > 0.0)
+        .collect();
+    // 稳定排序，权重相同时保持 PRECOMPRESSED_ENCODINGS 中的顺序
+    accepted.sort_by(|a, b| b.2.total_cmp(&a.2));
+    accepted
+        .into_iter()
+        .map(|(encoding, ext, _)| (encoding, ext))
+        .collect()
+}
+
+/// 尝试返回预压缩的同名文件
+///
+/// 按客户端偏好依次通过 `Storage::check_key_exists`（有缓存）检查 `{key}.br`、`{key}.gz`，
+/// 找到时返回该文件，并使用原文件推断的 Content-Type 和缓存策略（规则和扩展名按原文件匹配）、
+/// 对应的 Content-Encoding。
+/// 客户端不接受任何预压缩编码、原文件类型不在压缩 MIME 白名单内或没有预压缩文件时返回 `None`，
+/// 由调用方返回原文件。
+///
+/// # 参数
+///
+/// * `storage` - 存储后端 trait 对象
+/// * `method` - 请求方法，HEAD 只获取元数据
+/// * `headers` - 客户端请求头
+/// * `key` - 原文件在存储中的键
+/// * `site` - 站点配置，用于计算缓存策略
+/// * `mime_types` - 可压缩的 MIME 类型白名单（`compression.mime_types`）
+pub async fn fetch_precompressed(
+    storage: &Arc<dyn Storage>,
+    method: &Method,
+    headers: &HeaderMap,
+    key: &str,
+    site: &SiteConfig,
+    mime_types: &[String],
+) -> Result<Option<Response<Body>>, AppError> {
+    if PRECOMPRESSED_ENCODINGS
+        .iter()
+        .any(|(_, ext)| key.ends_with(&format!(".{ext}")))
+    {
+        return Ok(None);
+    }
+    let Some(content_type) =
+        guess_content_type(key).filter(|t| is_compressible_type(mime_types, t))
+    else {
+        return Ok(None);
+    };
+
+    for (encoding, ext) in accepted_encodings(headers) {
+        let sibling = format!("{key}.{ext}");
+        if !storage.check_key_exists(&sibling).await? {
+            continue;
+        }
+
+        let mut response = fetch_file_as(storage, method, headers, &sibling, key, site).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            continue;
+        }
+
+        let status = response.status();
+        let resp_headers = response.headers_mut();
+        if status.is_success() {
+            resp_headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+            // multipart/byteranges 响应保留自身的 Content-Type
+            let is_multipart = resp_headers
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.starts_with("multipart/"));
+            if !is_multipart && let Ok(value) = HeaderValue::from_str(&content_type) {
+                resp_headers.insert(CONTENT_TYPE, value);
+            }
+        }
+        resp_headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+        return Ok(Some(response));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{GetObjectOutput, MockStorage, ObjectMeta};
+    use axum::http::header::CACHE_CONTROL;
+    use mockall::predicate::{always, eq};
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, value.parse().unwrap());
+        headers
+    }
+
+    fn types() -> Vec<String> {
+        crate::config::CompressionConfig::default().mime_types
+    }
+
+    /// 测试 Accept-Encoding 解析
+    ///
+    /// 验证：
+    /// - 权重相同时 br 优先于 gzip
+    /// - 按 q 值排序，q=0 表示不接受
+    /// - `*` 作用于未明确列出的编码，`x-gzip` 视为 gzip
+    #[test]
+    fn test_accepted_encodings() {
+        let names = |value: &str| -> Vec<&str> {
+            accepted_encodings(&accept(value))
+                .into_iter()
+                .map(|(encoding, _)| encoding)
+                .collect()
+        };
+
+        assert_eq!(names("gzip, deflate, br"), ["br", "gzip"]);
+        assert_eq!(names("br;q=0.5, gzip;q=0.8"), ["gzip", "br"]);
+        assert_eq!(names("br;q=0, gzip"), ["gzip"]);
+        assert_eq!(names("*"), ["br", "gzip"]);
+        assert_eq!(names("*;q=0.1, gzip;q=0"), ["br"]);
+        assert_eq!(names("x-gzip"), ["gzip"]);
+        assert!(names("identity").is_empty());
+        assert!(accepted_encodings(&HeaderMap::new()).is_empty());
+    }
+
+    /// 测试返回预压缩文件
+    ///
+    /// 验证：
+    /// - 客户端接受 br 但只有 .gz 文件时返回 .gz 文件
+    /// - Content-Type 使用原文件推断的类型，Content-Encoding 为 gzip
+    /// - Cache-Control 按原文件的扩展名计算（HTML 为 no-cache）
+    /// - 带有 `Vary: Accept-Encoding`
+    #[tokio::test]
+    async fn test_fetch_precompressed_sibling() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .with(eq("www/index.html.br"))
+            .returning(|_| Ok(false));
+        mock_storage
+            .expect_check_key_exists()
+            .with(eq("www/index.html.gz"))
+            .returning(|_| Ok(true));
+        mock_storage
+            .expect_get_object()
+            .with(eq("www/index.html.gz"), always())
+            .returning(|_, _| {
+                let meta = ObjectMeta {
+                    content_type: Some("application/gzip".to_string()),
+                    ..ObjectMeta::default()
+                };
+                Ok(GetObjectOutput::full(meta, vec![0x1f, 0x8b]))
+            });
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
+
+        let resp = fetch_precompressed(
+            &storage,
+            &Method::GET,
+            &accept("br, gzip"),
+            "www/index.html",
+            &SiteConfig::default(),
+            &types(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "text/html");
+        assert_eq!(resp.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(resp.headers()[VARY], "accept-encoding");
+        assert_eq!(resp.headers()[CACHE_CONTROL], "no-cache");
+    }
+
+    /// 测试回退到原文件
+    ///
+    /// 验证：
+    /// - 客户端不接受压缩编码时不检查同名文件
+    /// - 不可压缩的类型（如 PNG）和不在配置的白名单内的类型不检查同名文件
+    /// - 没有同名文件时返回 None
+    #[tokio::test]
+    async fn test_fetch_precompressed_fallback() {
+        let site = SiteConfig::default();
+
+        let storage: Arc<dyn Storage> = Arc::new(MockStorage::new());
+        let plain = fetch_precompressed(
+            &storage,
+            &Method::GET,
+            &HeaderMap::new(),
+            "www/app.js",
+            &site,
+            &types(),
+        )
+        .await
+        .unwrap();
+        assert!(plain.is_none());
+        let image = fetch_precompressed(
+            &storage,
+            &Method::GET,
+            &accept("br"),
+            "www/logo.png",
+            &site,
+            &types(),
+        )
+        .await
+        .unwrap();
+        assert!(image.is_none());
+
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .times(2)
+            .returning(|_| Ok(false));
+        let storage: Arc<dyn Storage> = Arc::new(mock_storage);
+        let missing = fetch_precompressed(
+            &storage,
+            &Method::GET,
+            &accept("gzip, br"),
+            "www/app.js",
+            &site,
+            &types(),
+        )
+        .await
+        .unwrap();
+        assert!(missing.is_none());
+
+        let html_only = fetch_precompressed(
+            &storage,
+            &Method::GET,
+            &accept("br"),
+            "www/app.js",
+            &site,
+            &["text/html".to_string()],
+        )
+        .await
+        .unwrap();
+        assert!(html_only.is_none());
+    }
+}
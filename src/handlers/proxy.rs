@@ -0,0 +1,412 @@
+//! 反向代理路由
+//!
+//! 按路径前缀将请求转发到上游服务（如 SPA 的 `/api` 后端），使静态站点和 API
+//! 可以在同一个源下提供，无需在前面再部署 nginx。请求头和响应头过滤复用 `proxy_request`。
+
+use crate::config::{ConfigError, ProxyRouteConfig};
+use crate::error::AppError;
+use crate::handlers::files::proxy_request;
+use axum::body::{Body, HttpBody};
+use axum::extract::{OriginalUri, Request};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderValue, Response};
+use percent_encoding::percent_decode_str;
+
+/// 反向代理路由
+#[derive(Clone, Debug)]
+pub struct ProxyRoute {
+    /// 路径前缀，以 `/` 开头，不以 `/` 结尾
+    pub path: String,
+    /// 上游服务的基础 URL（不以 `/` 结尾）
+    upstream: String,
+    strip_prefix: bool,
+    /// 注入的 Authorization 头值（`Bearer {token}`）
+    authorization: Option<HeaderValue>,
+}
+
+impl ProxyRoute {
+    /// 根据已校验的配置创建代理路由
+    pub fn from_config(config: &ProxyRouteConfig) -> Result<Self, ConfigError> {
+        let authorization = config
+            .token
+            .as_ref()
+            .map(|token| {
+                let mut value =
+                    HeaderValue::from_str(&format!("Bearer {token}")).map_err(|_| {
+                        ConfigError::Invalid(format!("proxy '{}': invalid token", config.path))
+                    })?;
+                value.set_sensitive(true);
+                Ok::<_, ConfigError>(value)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            path: config.path.clone(),
+            upstream: config.upstream.trim_end_matches('/').to_string(),
+            strip_prefix: config.strip_prefix,
+            authorization,
+        })
+    }
+
+    /// 计算请求路径对应的上游 URL（不含查询参数）
+    ///
+    /// 开启 `strip_prefix` 时去掉路径前缀，前缀之后为空时转发到上游根路径 `/`。
+    /// 路径保持客户端的原始编码转发；包含 `.` / `..` 段（包括编码形式）、编码的 `/`、
+    /// 反斜杠或控制字符时返回 `AppError::InvalidPath`，否则 URL 解析时会解析这些段，
+    /// 使请求到达路由前缀或上游基础路径之外。
+    pub fn target_url(&self, path: &str) -> Result<String, AppError> {
+        check_path(path)?;
+        let path = if self.strip_prefix {
+            match path.strip_prefix(self.path.as_str()) {
+                Some("") | None => "/",
+                Some(rest) => rest,
+            }
+        } else {
+            path
+        };
+        Ok(format!("{}{}", self.upstream, path))
+    }
+}
+
+/// 检查转发的请求路径，逐段解码后拒绝无效的 UTF-8、`.` / `..` 段、路径分隔符和控制字符
+///
+/// 与 `normalize_path` 一样拒绝无效的 UTF-8，保证认证和签名中间件看到的路径与转发的路径一致。
+fn check_path(path: &str) -> Result<(), AppError> {
+    for segment in path.split('/') {
+        let decoded = percent_decode_str(segment)
+            .decode_utf8()
+            .map_err(|_| AppError::InvalidPath("not valid UTF-8".to_string()))?;
+        if matches!(decoded.as_ref(), "." | "..") {
+            return Err(AppError::InvalidPath("dot segment".to_string()));
+        }
+        if decoded.contains(['/', '\\']) {
+            return Err(AppError::InvalidPath("encoded separator".to_string()));
+        }
+        if decoded.chars().any(char::is_control) {
+            return Err(AppError::InvalidPath("control character".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// 将请求转发到代理路由的上游服务
+///
+/// 客户端提供了 Authorization 头时原样转发，否则注入路由配置的服务器 token（如果有）。
+/// 请求体和响应体均以流的形式传输。
+///
+/// # 参数
+///
+/// * `client` - reqwest HTTP 客户端
+/// * `route` - 匹配的代理路由
+/// * `req` - 客户端请求，嵌套路由下使用 `OriginalUri` 中的完整路径
+pub async fn forward(
+    client: &reqwest::Client,
+    route: &ProxyRoute,
+    req: Request,
+) -> Result<Response<Body>, AppError> {
+    let uri = req
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| req.uri().clone(), |original| original.0.clone());
+    let target_url = route.target_url(uri.path())?;
+    let query = uri.query().map(str::to_string);
+
+    let (parts, body) = req.into_parts();
+    let mut headers = parts.headers;
+    if !headers.contains_key(AUTHORIZATION)
+        && let Some(authorization) = &route.authorization
+    {
+        headers.insert(AUTHORIZATION, authorization.clone());
+    }
+
+    // 没有请求体时不发送空的流式请求体，避免 GET 请求带上 Transfer-Encoding: chunked
+    let body = (body.size_hint().exact() != Some(0))
+        .then(|| reqwest::Body::wrap_stream(body.into_data_stream()));
+
+    proxy_request(client, &target_url, parts.method, headers, query, body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use wiremock::matchers::{body_string, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn route(upstream: &str, strip_prefix: bool, token: Option<&str>) -> ProxyRoute {
+        ProxyRoute::from_config(&ProxyRouteConfig {
+            path: "/api".to_string(),
+            upstream: upstream.to_string(),
+            strip_prefix,
+            token: token.map(str::to_string),
+        })
+        .unwrap()
+    }
+
+    /// 测试上游 URL 的计算
+    ///
+    /// 验证：
+    /// - 不去掉前缀时保留完整路径
+    /// - 去掉前缀时只保留前缀之后的部分，为空时使用 `/`
+    /// - 上游 URL 的路径和末尾斜杠被正确拼接
+    #[test]
+    fn test_target_url() {
+        let keep = route("http://backend:8080/", false, None);
+        assert_eq!(
+            keep.target_url("/api/users").unwrap(),
+            "http://backend:8080/api/users"
+        );
+        assert_eq!(
+            keep.target_url("/api/a%20b/x.y..z").unwrap(),
+            "http://backend:8080/api/a%20b/x.y..z"
+        );
+
+        let strip = route("http://backend:8080/v1", true, None);
+        assert_eq!(
+            strip.target_url("/api/users").unwrap(),
+            "http://backend:8080/v1/users"
+        );
+        assert_eq!(strip.target_url("/api").unwrap(), "http://backend:8080/v1/");
+        assert_eq!(
+            strip.target_url("/api/").unwrap(),
+            "http://backend:8080/v1/"
+        );
+    }
+
+    /// 测试拒绝可能逃出路由前缀的路径
+    ///
+    /// 验证：
+    /// - `.` / `..` 段（包括百分号编码和大小写混合的形式）返回 400
+    /// - 编码的 `/`、反斜杠、控制字符和无效的 UTF-8 返回 400
+    /// - 请求不会到达上游服务
+    #[tokio::test]
+    async fn test_forward_rejects_dot_segments() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        for strip_prefix in [false, true] {
+            let route = route(&format!("{}/v1", mock_server.uri()), strip_prefix, None);
+            for uri in [
+                "/api/../admin",
+                "/api/%2e%2e/admin",
+                "/api/%2E./admin",
+                "/api/./users",
+                "/api/..%2Fadmin",
+                "/api/..%5Cadmin",
+                "/api/a\\..\\admin",
+                "/api/a%00b",
+                "/api/users%FF",
+            ] {
+                let error = route.target_url(uri).unwrap_err();
+                assert!(matches!(error, AppError::InvalidPath(_)), "{uri}");
+
+                let req = Request::builder().uri(uri).body(Body::empty());
+                // 原始反斜杠不是合法的 URI 字符，只检查 target_url
+                let Ok(req) = req else { continue };
+                let error = forward(&reqwest::Client::new(), &route, req)
+                    .await
+                    .unwrap_err();
+                assert!(matches!(error, AppError::InvalidPath(_)), "{uri}");
+            }
+        }
+    }
+
+    /// 测试请求转发和服务器 token 注入
+    ///
+    /// 验证：
+    /// - 请求方法、查询参数和请求体被转发
+    /// - 客户端未提供 Authorization 时注入服务器 token
+    #[tokio::test]
+    async fn test_forward_injects_token() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users"))
+            .and(query_param("page", "2"))
+            .and(header("authorization", "Bearer server-token"))
+            .and(body_string("{\"name\":\"a\"}"))
+            .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let route = route(&mock_server.uri(), true, Some("server-token"));
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/users?page=2")
+            .body(Body::from("{\"name\":\"a\"}"))
+            .unwrap();
+
+        let resp = forward(&reqwest::Client::new(), &route, req).await.unwrap();
+
+        assert_eq!(resp.status(), 201);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "created");
+    }
+
+    /// 测试客户端的 Authorization 头优先
+    ///
+    /// 验证：
+    /// - 客户端提供了 Authorization 时不被服务器 token 覆盖
+    /// - 没有请求体的 GET 请求不带 Transfer-Encoding
+    #[tokio::test]
+    async fn test_forward_keeps_client_authorization() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/me"))
+            .and(header("authorization", "Bearer client-token"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let route = route(&mock_server.uri(), false, Some("server-token"));
+        let req = Request::builder()
+            .uri("/api/me")
+            .header("authorization", "Bearer client-token")
+            .body(Body::empty())
+            .unwrap();
+
+        let resp = forward(&reqwest::Client::new(), &route, req).await.unwrap();
+
+        assert_eq!(resp.status(), 200);
+        let received = mock_server.received_requests().await.unwrap();
+        assert!(!received[0].headers.contains_key("transfer-encoding"));
+    }
+
+    /// 测试代理路由挂载到应用路由
+    ///
+    /// 验证：
+    /// - 路径前缀本身、带末尾斜杠和子路径都转发到上游
+    /// - 只有前缀相同但路径段不同的请求（`/apis`）由静态文件处理器处理
+    #[tokio::test]
+    async fn test_router_mounts_proxy_routes() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        let mut mock_storage = crate::storage::MockStorage::new();
+        mock_storage
+            .expect_get_object()
+            .with(
+                mockall::predicate::eq("www/apis"),
+                mockall::predicate::always(),
+            )
+            .returning(|_, _| {
+                Ok(crate::storage::GetObjectOutput::empty(
+                    axum::http::StatusCode::NOT_FOUND,
+                ))
+            });
+        mock_storage
+            .expect_check_key_exists()
+            .returning(|_| Ok(false));
+        let state = crate::AppState::new(std::sync::Arc::new(mock_storage), reqwest::Client::new());
+        let router = crate::router(state, vec![route(&mock_server.uri(), false, None)]);
+        let server = axum_test::TestServer::new(router).unwrap();
+
+        for uri in ["/api", "/api/", "/api/users/1"] {
+            server
+                .delete(uri)
+                .await
+                .assert_status(axum::http::StatusCode::NO_CONTENT);
+        }
+        server
+            .get("/apis")
+            .await
+            .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+        let paths: Vec<String> = mock_server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.url.path().to_string())
+            .collect();
+        assert_eq!(paths, ["/api", "/api/", "/api/users/1"]);
+    }
+
+    /// 测试认证和签名 URL 规则同样保护代理路由
+    ///
+    /// 验证：
+    /// - 代理路径匹配认证规则时，没有凭据返回 401，请求不会到达上游
+    /// - 认证通过后转发请求，客户端的凭据被替换为路由配置的 token，响应标记为 `private`
+    /// - 代理路径匹配签名 URL 规则时，没有签名返回 403
+    /// - 无法规范化的路径（如无效的 UTF-8）返回 400，不会绕过认证和签名校验
+    #[tokio::test]
+    async fn test_router_protects_proxy_routes() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/users"))
+            .and(header("authorization", "Bearer upstream-token"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let site = crate::config::SiteConfig {
+            auth: vec![crate::config::AuthRule {
+                path: "/api".to_string(),
+                realm: "API".to_string(),
+                htpasswd: None,
+                tokens: vec!["site-token".to_string()],
+                jwt: None,
+            }],
+            signed_urls: crate::config::SignedUrlConfig {
+                paths: vec!["/api/private".to_string()],
+                keys: vec![crate::config::SigningKey {
+                    id: "k1".to_string(),
+                    secret: "secret-0123456789".to_string(),
+                }],
+                ..crate::config::SignedUrlConfig::default()
+            },
+            ..crate::config::SiteConfig::default()
+        };
+        let state = crate::AppState::with_site(
+            std::sync::Arc::new(crate::storage::MockStorage::new()),
+            reqwest::Client::new(),
+            site,
+        );
+        let router = crate::router(
+            state,
+            vec![route(&mock_server.uri(), false, Some("upstream-token"))],
+        );
+        let server = axum_test::TestServer::new(router).unwrap();
+
+        for uri in ["/api", "/api/users", "/api/%75sers"] {
+            server
+                .get(uri)
+                .await
+                .assert_status(StatusCode::UNAUTHORIZED);
+        }
+        server
+            .get("/api/users%FF")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        let resp = server
+            .get("/api/users")
+            .add_header(AUTHORIZATION, "Bearer site-token")
+            .await;
+        resp.assert_status(StatusCode::OK);
+        assert_eq!(resp.header("cache-control"), "private");
+
+        server
+            .get("/api/private/report")
+            .add_header(AUTHORIZATION, "Bearer site-token")
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        server
+            .get("/api/private/report%FF")
+            .add_header(AUTHORIZATION, "Bearer site-token")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
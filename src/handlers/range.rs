@@ -0,0 +1,368 @@
+//! HTTP Range 请求处理
+//!
+//! 解析 `Range` / `If-Range` 请求头，并根据对象大小计算需要返回的字节区间。
+//! 多区间请求以 `multipart/byteranges` 格式返回，相关的分段格式也在此定义。
+
+use crate::storage::ObjectMeta;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// 单个请求允许的最大区间数量，超过时忽略 Range 头返回完整内容，
+/// 避免大量细碎区间放大上游请求
+pub const MAX_RANGES: usize = 32;
+
+/// 字节区间（闭区间，`start` 和 `end` 均包含在内）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// 区间长度（字节），区间至少包含一个字节
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// 用于转发给上游的 Range 头值，如 `bytes=0-499`
+    pub fn to_header_value(&self) -> String {
+        format!("bytes={}-{}", self.start, self.end)
+    }
+
+    /// Content-Range 头值，如 `bytes 0-499/1234`
+    pub fn content_range(&self, size: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, size)
+    }
+}
+
+/// Range 请求头的解析结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// 忽略 Range 头，返回完整内容（语法错误、单位不支持或区间过多）
+    Full,
+    /// 返回部分内容，区间已排序并合并重叠部分
+    Partial(Vec<ByteRange>),
+    /// 所有区间都无法满足，应返回 416
+    Unsatisfiable,
+}
+
+/// 解析 Range 请求头
+///
+/// 按照 RFC 9110 的语义处理：
+/// - 语法错误或非 `bytes` 单位时忽略该头（返回 `Full`）
+/// - 起始位置超出对象大小的区间被丢弃，全部丢弃时返回 `Unsatisfiable`
+/// - 结束位置超出对象大小时截断到最后一个字节
+/// - 重叠或相邻的区间会被合并
+///
+/// # 参数
+///
+/// * `value` - Range 请求头的值，如 `bytes=0-499,-500`
+/// * `size` - 对象大小（字节）
+pub fn parse_range(value: &str, size: u64) -> RangeRequest {
+    let Some((unit, specs)) = value.split_once('=') else {
+        return RangeRequest::Full;
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return RangeRequest::Full;
+    }
+
+    let mut spec_count = 0;
+    let mut ranges = Vec::new();
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        spec_count += 1;
+        if spec_count > MAX_RANGES {
+            return RangeRequest::Full;
+        }
+
+        let Some((first, last)) = spec.split_once('-') else {
+            return RangeRequest::Full;
+        };
+        let (first, last) = (first.trim(), last.trim());
+
+        if first.is_empty() {
+            // 后缀区间：bytes=-500 表示最后 500 个字节
+            let Some(suffix) = parse_number(last) else {
+                return RangeRequest::Full;
+            };
+            if suffix == 0 || size == 0 {
+                continue;
+            }
+            ranges.push(ByteRange {
+                start: size.saturating_sub(suffix),
+                end: size - 1,
+            });
+            continue;
+        }
+
+        let Some(start) = parse_number(first) else {
+            return RangeRequest::Full;
+        };
+        let end = if last.is_empty() {
+            None
+        } else {
+            match parse_number(last) {
+                Some(end) if end >= start => Some(end),
+                _ => return RangeRequest::Full,
+            }
+        };
+        if start >= size {
+            continue;
+        }
+        ranges.push(ByteRange {
+            start,
+            end: end.map_or(size - 1, |end| end.min(size - 1)),
+        });
+    }
+
+    if spec_count == 0 {
+        return RangeRequest::Full;
+    }
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    RangeRequest::Partial(merged)
+}
+
+/// 解析十进制非负整数（不接受符号和空白）
+fn parse_number(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// 判断 If-Range 条件是否满足
+///
+/// If-Range 可以是 ETag 或 HTTP 日期：
+/// - ETag 使用强比较，弱 ETag（`W/` 前缀）永远不匹配
+/// - 日期必须与对象的 Last-Modified 完全相等（精确到秒）
+///
+/// 条件不满足时应忽略 Range 头并返回完整内容。
+pub fn if_range_matches(value: &str, meta: &ObjectMeta) -> bool {
+    let value = value.trim();
+
+    if value.starts_with("W/") {
+        return false;
+    }
+    if value.starts_with('"') {
+        return meta
+            .etag
+            .as_deref()
+            .is_some_and(|etag| !etag.starts_with("W/") && etag == value);
+    }
+
+    let (Ok(date), Some(last_modified)) = (httpdate::parse_http_date(value), meta.last_modified)
+    else {
+        return false;
+    };
+    let secs = |t: std::time::SystemTime| t.duration_since(UNIX_EPOCH).map(|d| d.as_secs());
+    matches!((secs(date), secs(last_modified)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// 生成 multipart/byteranges 的分隔符
+pub fn generate_boundary() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let seed = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let hash = std::collections::hash_map::RandomState::new().hash_one(seed);
+    format!("{hash:016x}{seed:08x}")
+}
+
+/// multipart/byteranges 响应的 Content-Type 头值
+pub fn multipart_content_type(boundary: &str) -> String {
+    format!("multipart/byteranges; boundary={boundary}")
+}
+
+/// 单个分段的头部（分隔符 + 分段头 + 空行）
+pub fn multipart_part_header(
+    boundary: &str,
+    content_type: &str,
+    range: &ByteRange,
+    size: u64,
+) -> String {
+    format!(
+        "\r\n--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: {}\r\n\r\n",
+        range.content_range(size)
+    )
+}
+
+/// multipart/byteranges 的结束分隔符
+pub fn multipart_end(boundary: &str) -> String {
+    format!("\r\n--{boundary}--\r\n")
+}
+
+/// 计算 multipart/byteranges 响应体的总长度，用于设置 Content-Length
+pub fn multipart_length(
+    boundary: &str,
+    content_type: &str,
+    ranges: &[ByteRange],
+    size: u64,
+) -> u64 {
+    let parts: u64 = ranges
+        .iter()
+        .map(|r| multipart_part_header(boundary, content_type, r, size).len() as u64 + r.len())
+        .sum();
+    parts + multipart_end(boundary).len() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    /// 测试单个区间的解析
+    ///
+    /// 验证：
+    /// - 普通区间、开放区间、后缀区间均能正确解析
+    /// - 结束位置超出对象大小时被截断
+    #[test]
+    fn test_parse_range_single() {
+        let one = |start, end| RangeRequest::Partial(vec![ByteRange { start, end }]);
+
+        assert_eq!(parse_range("bytes=0-499", 1000), one(0, 499));
+        assert_eq!(parse_range("bytes=500-", 1000), one(500, 999));
+        assert_eq!(parse_range("bytes=-200", 1000), one(800, 999));
+        assert_eq!(parse_range("bytes=-2000", 1000), one(0, 999));
+        assert_eq!(parse_range("bytes=900-5000", 1000), one(900, 999));
+        assert_eq!(parse_range("Bytes = 0-0", 1000), one(0, 0));
+    }
+
+    /// 测试多个区间的解析
+    ///
+    /// 验证：
+    /// - 多个区间按起始位置排序
+    /// - 重叠和相邻的区间被合并
+    #[test]
+    fn test_parse_range_multiple() {
+        assert_eq!(
+            parse_range("bytes=500-599, 0-99", 1000),
+            RangeRequest::Partial(vec![
+                ByteRange { start: 0, end: 99 },
+                ByteRange {
+                    start: 500,
+                    end: 599
+                },
+            ])
+        );
+        assert_eq!(
+            parse_range("bytes=0-99,50-199,200-299", 1000),
+            RangeRequest::Partial(vec![ByteRange { start: 0, end: 299 }])
+        );
+    }
+
+    /// 测试无法满足的区间
+    ///
+    /// 验证：
+    /// - 起始位置超出对象大小时返回 Unsatisfiable
+    /// - 空对象上的任何区间都无法满足
+    /// - 部分区间可满足时丢弃无法满足的区间
+    #[test]
+    fn test_parse_range_unsatisfiable() {
+        assert_eq!(
+            parse_range("bytes=1000-", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(parse_range("bytes=-0", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-10", 0), RangeRequest::Unsatisfiable);
+        assert_eq!(
+            parse_range("bytes=2000-3000,0-9", 1000),
+            RangeRequest::Partial(vec![ByteRange { start: 0, end: 9 }])
+        );
+    }
+
+    /// 测试无效的 Range 头被忽略
+    ///
+    /// 验证：
+    /// - 语法错误、非 bytes 单位、逆序区间都返回 Full
+    /// - 区间数量超过 MAX_RANGES 时返回 Full
+    #[test]
+    fn test_parse_range_invalid() {
+        for value in [
+            "bytes",
+            "bytes=",
+            "items=0-10",
+            "bytes=abc",
+            "bytes=10-5",
+            "bytes=+1-5",
+            "bytes=0-1;2-3",
+        ] {
+            assert_eq!(parse_range(value, 1000), RangeRequest::Full, "{value}");
+        }
+
+        let many = (0..=MAX_RANGES)
+            .map(|i| format!("{}-{}", i * 10, i * 10 + 1))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(
+            parse_range(&format!("bytes={many}"), 10_000),
+            RangeRequest::Full
+        );
+    }
+
+    /// 测试 If-Range 条件判断
+    ///
+    /// 验证：
+    /// - 强 ETag 完全相等时匹配，弱 ETag 永不匹配
+    /// - HTTP 日期与 Last-Modified 相等时匹配
+    #[test]
+    fn test_if_range_matches() {
+        let last_modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let meta = ObjectMeta {
+            content_length: 10,
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some(last_modified),
+            ..ObjectMeta::default()
+        };
+
+        assert!(if_range_matches("\"abc\"", &meta));
+        assert!(!if_range_matches("\"xyz\"", &meta));
+        assert!(!if_range_matches("W/\"abc\"", &meta));
+        assert!(if_range_matches(
+            &httpdate::fmt_http_date(last_modified),
+            &meta
+        ));
+        assert!(!if_range_matches(
+            &httpdate::fmt_http_date(last_modified + Duration::from_secs(1)),
+            &meta
+        ));
+        assert!(!if_range_matches("not a date", &meta));
+    }
+
+    /// 测试 multipart/byteranges 长度计算
+    ///
+    /// 验证：
+    /// - 计算出的长度与实际拼接出的响应体长度一致
+    #[test]
+    fn test_multipart_length() {
+        let boundary = generate_boundary();
+        let ranges = [
+            ByteRange { start: 0, end: 3 },
+            ByteRange { start: 8, end: 9 },
+        ];
+
+        let mut body = String::new();
+        for range in &ranges {
+            body.push_str(&multipart_part_header(&boundary, "text/plain", range, 10));
+            body.push_str(&"x".repeat(range.len() as usize));
+        }
+        body.push_str(&multipart_end(&boundary));
+
+        assert_eq!(
+            multipart_length(&boundary, "text/plain", &ranges, 10),
+            body.len() as u64
+        );
+    }
+}
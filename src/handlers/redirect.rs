@@ -0,0 +1,245 @@
+//! 重定向模式
+//!
+//! 对匹配重定向规则的路径，不经本服务转发文件内容，而是返回指向对象预签名 URL 的
+//! 302 / 307，由客户端直接从对象存储下载。本地目录存储不支持预签名 URL，不会重定向。
+
+use crate::config::{Disposition, RedirectRule};
+use crate::error::AppError;
+use crate::storage::{PresignOptions, Storage};
+use axum::body::Body;
+use axum::http::header::{CACHE_CONTROL, LOCATION};
+use axum::http::{Response, StatusCode};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use std::time::Duration;
+
+/// RFC 5987 `attr-char` 之外需要编码的字符
+const FILENAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// 根据对象键生成 Content-Disposition 头值
+///
+/// 文件名取对象键的最后一段：`filename` 参数只保留 ASCII（其余字符替换为 `_`），
+/// 完整的 UTF-8 文件名通过 `filename*` 参数提供。
+pub fn content_disposition(disposition: Disposition, key: &str) -> String {
+    let kind = match disposition {
+        Disposition::Inline => "inline",
+        Disposition::Attachment => "attachment",
+    };
+    let filename = key.rsplit('/').next().unwrap_or(key);
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded = utf8_percent_encode(filename, FILENAME_ENCODE_SET);
+    format!("{kind}; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+/// 返回重定向到对象预签名 URL 的响应
+///
+/// 存储不支持预签名 URL（如本地目录存储）时返回 `None`，由调用方转发文件内容。预签名 URL 有有效期，重定向响应不允许缓存。
+///
+/// # 参数
+///
+/// * `storage` - 存储后端 trait 对象
+/// * `key` - 已确认存在的对象键
+/// * `rule` - 匹配的重定向规则
+pub async fn presigned_redirect(
+    storage: &dyn Storage,
+    key: &str,
+    rule: &RedirectRule,
+) -> Result<Option<Response<Body>>, AppError> {
+    let options = PresignOptions {
+        expires_in: Duration::from_secs(rule.expires_in),
+        response_content_disposition: rule.disposition.map(|d| content_disposition(d, key)),
+        ..PresignOptions::default()
+    };
+    let url = match storage.get_presigned_url_with(key, &options).await {
+        Ok(url) => url,
+        Err(AppError::PresignUnsupported) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let status = StatusCode::from_u16(rule.status).unwrap_or(StatusCode::FOUND);
+    Ok(Some(
+        Response::builder()
+            .status(status)
+            .header(LOCATION, url)
+            .header(CACHE_CONTROL, "no-store")
+            .body(Body::empty())?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppState;
+    use crate::config::{PathPattern, SiteConfig};
+    use crate::storage::{GetObjectOutput, MockStorage};
+    use axum::http::header::ACCEPT;
+    use mockall::predicate::{always, eq};
+    use std::sync::Arc;
+
+    fn rule(pattern: &str, status: u16, disposition: Option<Disposition>) -> RedirectRule {
+        RedirectRule {
+            pattern: PathPattern::new(pattern).unwrap(),
+            status,
+            expires_in: 600,
+            disposition,
+        }
+    }
+
+    /// 测试 Content-Disposition 的生成
+    ///
+    /// 验证：
+    /// - 文件名取对象键的最后一段
+    /// - 非 ASCII 字符和引号在 `filename` 中被替换，在 `filename*` 中被百分号编码
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition(Disposition::Attachment, "www/downloads/app-1.0.zip"),
+            "attachment; filename=\"app-1.0.zip\"; filename*=UTF-8''app-1.0.zip"
+        );
+        assert_eq!(
+            content_disposition(Disposition::Inline, "www/报告 \"v2\".pdf"),
+            "inline; filename=\"__ _v2_.pdf\"; filename*=UTF-8''%E6%8A%A5%E5%91%8A%20%22v2%22.pdf"
+        );
+    }
+
+    /// 测试重定向模式的请求处理
+    ///
+    /// 验证：
+    /// - 匹配规则的路径返回配置的状态码和预签名 URL，带 `Cache-Control: no-store`
+    /// - 预签名 URL 使用规则的有效期和 Content-Disposition
+    /// - 不匹配规则的路径照常转发文件内容
+    #[tokio::test]
+    async fn test_handle_files_redirect() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .with(eq("www/downloads/app.zip"))
+            .returning(|_| Ok(true));
+        mock_storage
+            .expect_get_presigned_url_with()
+            .with(
+                eq("www/downloads/app.zip"),
+                eq(PresignOptions {
+                    expires_in: Duration::from_secs(600),
+                    response_content_disposition: Some(
+                        "attachment; filename=\"app.zip\"; filename*=UTF-8''app.zip".to_string(),
+                    ),
+                    ..PresignOptions::default()
+                }),
+            )
+            .returning(|_, _| {
+                Ok(
+                    "https://bucket.s3.example.com/www/downloads/app.zip?X-Amz-Signature=abc"
+                        .to_string(),
+                )
+            });
+        mock_storage
+            .expect_get_object()
+            .with(eq("www/app.js"), always())
+            .returning(|_, _| Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND)));
+        mock_storage
+            .expect_check_key_exists()
+            .returning(|_| Ok(false));
+
+        let site = SiteConfig {
+            redirect: vec![rule("downloads/**", 307, Some(Disposition::Attachment))],
+            ..SiteConfig::default()
+        };
+        let state = AppState::with_site(Arc::new(mock_storage), reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        let resp = server.get("/downloads/app.zip").await;
+        resp.assert_status(StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            resp.header(LOCATION),
+            "https://bucket.s3.example.com/www/downloads/app.zip?X-Amz-Signature=abc"
+        );
+        assert_eq!(resp.header(CACHE_CONTROL), "no-store");
+
+        server
+            .get("/app.js")
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    /// 测试 SPA 回退和本地存储的重定向
+    ///
+    /// 验证：
+    /// - 匹配规则的路径不存在时，页面导航请求重定向到 SPA 回退找到的索引文件
+    /// - 存储不支持预签名 URL 时不重定向，而是返回文件内容
+    #[tokio::test]
+    async fn test_handle_files_redirect_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("files")).unwrap();
+        std::fs::write(dir.path().join("files/index.html"), "files").unwrap();
+
+        let site = SiteConfig {
+            redirect: vec![rule("files/**", 302, None)],
+            ..SiteConfig::default()
+        };
+        let storage = crate::storage::FsStorage::new(dir.path(), "www").unwrap();
+        let state = AppState::with_site(Arc::new(storage), reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        let resp = server
+            .get("/files/missing.bin")
+            .add_header(ACCEPT, "text/html")
+            .await;
+        resp.assert_status(StatusCode::OK);
+        assert_eq!(resp.text(), "files");
+
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .with(eq("www/files/index.html"))
+            .returning(|_| Ok(true));
+        mock_storage
+            .expect_check_key_exists()
+            .returning(|_| Ok(false));
+        mock_storage
+            .expect_get_presigned_url_with()
+            .with(
+                eq("www/files/index.html"),
+                eq(PresignOptions {
+                    expires_in: Duration::from_secs(600),
+                    ..PresignOptions::default()
+                }),
+            )
+            .returning(|_, _| Ok("https://cdn.example.com/www/files/index.html".to_string()));
+
+        let site = SiteConfig {
+            redirect: vec![rule("files/**", 302, None)],
+            ..SiteConfig::default()
+        };
+        let state = AppState::with_site(Arc::new(mock_storage), reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        let resp = server
+            .get("/files/missing.bin")
+            .add_header(ACCEPT, "text/html")
+            .await;
+        resp.assert_status(StatusCode::FOUND);
+        assert_eq!(
+            resp.header(LOCATION),
+            "https://cdn.example.com/www/files/index.html"
+        );
+    }
+}
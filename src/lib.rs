@@ -4,48 +4,117 @@
 //! - 从S3存储桶服务静态文件
 //! - 支持SPA(Single Page Application)路由
 //! - 提供文件缓存和代理功能
-//! - 支持CORS跨域请求
+//! - 支持按站点和路径配置的CORS跨域策略
 
+pub mod auth;
+pub mod compression;
+pub mod config;
+pub mod cors;
 pub mod error;
 pub mod handlers;
+pub mod signed_url;
+pub mod site;
 pub mod storage;
 
-use axum::routing::get;
+use axum::extract::{Request, State};
+use axum::routing::any;
+use config::{Config, ConfigError, SiteConfig};
+use handlers::proxy::ProxyRoute;
+use site::{Site, VirtualHosts};
 use std::sync::Arc;
-use storage::{S3Storage, Storage};
-use tower_http::cors::CorsLayer;
+use storage::Storage;
 use tower_http::trace::TraceLayer;
 
-/// 应用状态 - 使用 Arc 包装 Storage trait 对象
+/// 应用状态 - 虚拟主机表（各站点的存储后端和配置）和共享的 HTTP 客户端
 #[derive(Clone)]
 pub struct AppState {
-    pub storage: Arc<dyn Storage>,
+    pub hosts: Arc<VirtualHosts>,
     pub http_client: reqwest::Client,
 }
 
-/// 创建应用（生产环境）
-pub async fn app() -> axum::Router {
-    // 初始化 S3 客户端
-    let s3_config = aws_config::load_from_env().await;
-    let s3_client = Arc::new(aws_sdk_s3::Client::new(&s3_config));
+impl AppState {
+    /// 使用默认站点配置创建只有一个站点的应用状态
+    pub fn new(storage: Arc<dyn Storage>, http_client: reqwest::Client) -> Self {
+        Self::with_site(storage, http_client, SiteConfig::default())
+    }
 
-    // 从环境变量读取 S3 存储桶名称
-    let bucket_name =
-        std::env::var("AWS_BUCKET").expect("AWS_BUCKET environment variable must be set");
+    /// 使用指定的站点配置创建只有一个站点的应用状态
+    pub fn with_site(
+        storage: Arc<dyn Storage>,
+        http_client: reqwest::Client,
+        site: SiteConfig,
+    ) -> Self {
+        Self {
+            hosts: Arc::new(VirtualHosts::new(Site::new(storage, site))),
+            http_client,
+        }
+    }
+}
 
-    let storage = S3Storage::new(s3_client, bucket_name);
+/// 创建应用（生产环境）
+///
+/// 默认站点配置了本地目录（`storage.root`）时使用本地目录作为存储（便于本地开发），
+/// 否则从 `storage.bucket` 指定的 S3 存储桶提供文件；
+/// `hosts` 中配置的虚拟主机按 Host 头选择各自的存储和站点配置，
+/// `proxy` 中配置的路径前缀转发到上游服务，响应按 `compression` 配置压缩；
+/// 使用 S3 存储时按 `storage.cache.stats_interval` 定期记录缓存命中统计。
+pub async fn app(config: &Config) -> Result<axum::Router, ConfigError> {
+    let hosts = VirtualHosts::from_config(config).await?;
+    let proxy_routes = config
+        .proxy
+        .iter()
+        .map(ProxyRoute::from_config)
+        .collect::<Result<Vec<_>, _>>()?;
 
     // 初始化 HTTP 客户端
     let http_client = reqwest::Client::new();
 
     let state = AppState {
-        storage: Arc::new(storage),
+        hosts: Arc::new(hosts),
         http_client,
     };
 
-    axum::Router::new()
-        .fallback(get(handlers::files::handle_files))
-        .with_state(state)
+    let stats_interval = config.storage.cache.stats_interval;
+    if stats_interval > 0 && !state.hosts.cache_stats().is_empty() {
+        tokio::spawn(
+            state
+                .hosts
+                .clone()
+                .log_cache_stats(std::time::Duration::from_secs(stats_interval)),
+        );
+    }
+
+    Ok(compression::compress(router(state, proxy_routes), &config.compression))
+}
+
+/// 根据应用状态和反向代理路由表构建路由
+///
+/// 代理路由匹配路径前缀本身及其下的所有路径，支持所有 HTTP 方法；
+/// 其余 GET 请求由静态文件处理器处理。CORS、认证和签名 URL 中间件作用于所有路由（包括代理路由），
+/// 预检请求在认证之前应答。
+pub fn router(state: AppState, proxy_routes: Vec<ProxyRoute>) -> axum::Router {
+    let mut router = axum::Router::new();
+    for route in proxy_routes {
+        tracing::info!("Proxying {} to upstream", route.path);
+        let path = route.path.clone();
+        let handler = any(move |State(state): State<AppState>, req: Request| {
+            let route = route.clone();
+            async move { handlers::proxy::forward(&state.http_client, &route, req).await }
+        });
+        router = router.nest_service(&path, handler.with_state(state.clone()));
+    }
+
+    router
+        .fallback(handlers::error_pages::handle_files_with_error_pages)
+        .with_state(state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            signed_url::require_signature,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_auth,
+        ))
+        .layer(axum::middleware::from_fn_with_state(state, cors::apply_cors))
         .layer(TraceLayer::new_for_http())
-        .layer(CorsLayer::permissive())
 }
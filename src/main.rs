@@ -1,24 +1,34 @@
+use clap::Parser;
 use static_server::app;
-use std::net::SocketAddr;
+use static_server::config::{Cli, Command, Config, LogFormat};
+use static_server::signed_url::sign_command;
 use tokio::net::TcpListener;
-use tracing::Level;
 use tracing_subscriber::fmt::time::LocalTime;
 
 #[tokio::main]
-async fn main() -> std::io::Result<()> {
+async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
 
-    tracing_subscriber::fmt()
-        .pretty()
+    let cli = Cli::parse();
+    let config = Config::load(&cli)?;
+
+    if let Some(Command::Sign(args)) = &cli.command {
+        println!("{}", sign_command(&config, args)?);
+        return Ok(());
+    }
+
+    let builder = tracing_subscriber::fmt()
         .with_timer(LocalTime::rfc_3339())
-        .with_max_level(Level::DEBUG)
-        .init();
+        .with_max_level(tracing::Level::from(config.log.level));
+    match config.log.format {
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Compact => builder.compact().init(),
+        LogFormat::Json => builder.json().init(),
+    }
 
-    let app = app().await;
+    let app = app(&config).await?;
 
-    let addr: SocketAddr = "0.0.0.0:3000"
-        .parse()
-        .expect("Failed to parse socket address: invalid address format");
+    let addr = config.listen;
     tracing::info!("Server running on {}", addr);
 
     axum::serve(TcpListener::bind(addr).await?, app).await?;
@@ -0,0 +1,542 @@
+//! 签名 URL
+//!
+//! 配置的私有路径下的请求需要带 `expires`（Unix 时间戳）、`kid`（密钥 ID）和 `signature` 查询参数。
+//! 签名为 HMAC-SHA256(密钥, "{expires}\n{path}") 的 base64url 编码（无填充），
+//! `path` 为规范化后的请求路径（以 `/` 开头），因此同一文件的不同编码形式共用一个签名。
+//! 校验在读取存储之前进行，签名无效或已过期时返回 403。
+//! 配置多个密钥时全部用于校验，第一个用于生成新的签名，便于轮换。
+
+use crate::AppState;
+use crate::auth::mark_private;
+use crate::config::{Config, PathCase, SignArgs, SignedUrlConfig};
+use crate::error::builtin_error_page;
+use crate::handlers::path::normalize_path;
+use axum::extract::{Query, Request, State};
+use axum::http::header::CACHE_CONTROL;
+use axum::http::{HeaderValue, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 过期时间（Unix 时间戳，秒）的查询参数名
+pub const EXPIRES_PARAM: &str = "expires";
+/// 密钥 ID 的查询参数名
+pub const KEY_ID_PARAM: &str = "kid";
+/// 签名的查询参数名
+pub const SIGNATURE_PARAM: &str = "signature";
+
+/// 路径段中需要编码的字符（RFC 3986 `unreserved` 之外的字符）
+const PATH_SEGMENT_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 生成签名 URL 的错误
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SignError {
+    /// 站点没有配置签名密钥
+    #[error("no signing key is configured for this site")]
+    NoKeys,
+
+    /// 指定的密钥不存在
+    #[error("unknown signing key '{0}'")]
+    UnknownKey(String),
+
+    /// 请求路径无效
+    #[error("invalid path '{0}'")]
+    InvalidPath(String),
+}
+
+/// 签名校验失败的原因（只记录到日志，客户端统一收到 403）
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+enum Rejected {
+    #[error("missing or malformed signature parameters")]
+    Missing,
+    #[error("unknown key '{0}'")]
+    UnknownKey(String),
+    #[error("expired at {0}")]
+    Expired(u64),
+    #[error("signature mismatch")]
+    Mismatch,
+}
+
+/// 签名 URL 的查询参数
+#[derive(Deserialize)]
+struct SignatureParams {
+    expires: u64,
+    kid: String,
+    signature: String,
+}
+
+/// 当前 Unix 时间戳（秒）
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// 计算 HMAC-SHA256(secret, "{expires}\n{path}")
+fn mac(secret: &str, path: &str, expires: u64) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{expires}\n{path}").as_bytes());
+    mac
+}
+
+/// 生成签名 URL 的路径和查询参数，如 `/private/report.pdf?expires=...&kid=...&signature=...`
+///
+/// # 参数
+///
+/// * `config` - 站点的签名 URL 配置
+/// * `path` - 请求路径，按站点配置规范化后签名
+/// * `case` - 站点的路径大小写处理
+/// * `expires` - 过期时间（Unix 时间戳，秒）
+/// * `key_id` - 使用的密钥 ID，`None` 时使用第一个密钥
+pub fn sign(
+    config: &SignedUrlConfig,
+    path: &str,
+    case: PathCase,
+    expires: u64,
+    key_id: Option<&str>,
+) -> Result<String, SignError> {
+    let key = match key_id {
+        Some(id) => config
+            .key(id)
+            .ok_or_else(|| SignError::UnknownKey(id.to_string()))?,
+        None => config.keys.first().ok_or(SignError::NoKeys)?,
+    };
+    let path = normalize_path(path, case).map_err(|_| SignError::InvalidPath(path.to_string()))?;
+    let signature = URL_SAFE_NO_PAD.encode(
+        mac(&key.secret, &format!("/{path}"), expires)
+            .finalize()
+            .into_bytes(),
+    );
+    let encoded: Vec<String> = path
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT_ENCODE_SET).to_string())
+        .collect();
+    Ok(format!(
+        "/{}?{EXPIRES_PARAM}={expires}&{KEY_ID_PARAM}={}&{SIGNATURE_PARAM}={signature}",
+        encoded.join("/"),
+        key.id
+    ))
+}
+
+/// `sign` 子命令：按参数选择站点和密钥，返回签名 URL
+pub fn sign_command(config: &Config, args: &SignArgs) -> Result<String, SignError> {
+    let site = match &args.host {
+        Some(host) => config.site_for_host(host),
+        None => &config.site,
+    };
+    let expires = unix_now().saturating_add(args.expires_in);
+    let url = sign(
+        &site.signed_urls,
+        &args.path,
+        site.path_case,
+        expires,
+        args.key_id.as_deref(),
+    )?;
+    Ok(match &args.base_url {
+        Some(base_url) => format!("{}{url}", base_url.trim_end_matches('/')),
+        None => url,
+    })
+}
+
+/// 校验请求的签名，成功时返回签名的过期时间
+///
+/// `path` 为规范化后的请求路径（不含开头的 `/`）；过期时间加上允许的时钟偏差后早于 `now` 时视为过期。
+fn verify(config: &SignedUrlConfig, path: &str, uri: &Uri, now: u64) -> Result<u64, Rejected> {
+    let Ok(Query(params)) = Query::<SignatureParams>::try_from_uri(uri) else {
+        return Err(Rejected::Missing);
+    };
+    let Some(key) = config.key(&params.kid) else {
+        return Err(Rejected::UnknownKey(params.kid));
+    };
+    if params.expires.saturating_add(config.clock_skew) < now {
+        return Err(Rejected::Expired(params.expires));
+    }
+    let signature = URL_SAFE_NO_PAD
+        .decode(params.signature.trim_end_matches('='))
+        .map_err(|_| Rejected::Mismatch)?;
+    mac(&key.secret, &format!("/{path}"), params.expires)
+        .verify_slice(&signature)
+        .map_err(|_| Rejected::Mismatch)?;
+    Ok(params.expires)
+}
+
+/// 限制签名响应的缓存时间：标记为私有缓存，`max-age` 不超过签名的剩余有效期
+///
+/// 带 `no-store` 的响应不再添加 `max-age`。
+fn limit_cache_lifetime(response: &mut Response, remaining: u64) {
+    mark_private(response);
+    let Some(value) = response
+        .headers()
+        .get(CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return;
+    };
+    let directives: Vec<_> = value.split(',').map(str::trim).collect();
+    if directives
+        .iter()
+        .any(|d| d.eq_ignore_ascii_case("no-store"))
+    {
+        return;
+    }
+    let mut max_age = remaining;
+    let mut limited: Vec<_> = directives
+        .into_iter()
+        .filter(|d| {
+            let Some((name, value)) = d.split_once('=') else {
+                return true;
+            };
+            if !name.trim().eq_ignore_ascii_case("max-age") {
+                return true;
+            }
+            if let Ok(value) = value.trim().trim_matches('"').parse::<u64>() {
+                max_age = max_age.min(value);
+            }
+            false
+        })
+        .map(str::to_string)
+        .collect();
+    // `mark_private` 保证第一个指令是 `private`
+    limited.insert(1, format!("max-age={max_age}"));
+    if let Ok(value) = HeaderValue::from_str(&limited.join(", ")) {
+        response.headers_mut().insert(CACHE_CONTROL, value);
+    }
+}
+
+/// 签名 URL 中间件：请求路径位于站点配置的私有路径下时校验签名
+///
+/// 路径按站点配置规范化后再匹配和校验；无效路径直接返回 400，不会绕过签名校验。
+/// 校验通过的响应不允许被共享缓存保存，浏览器的缓存时间也不超过签名的剩余有效期，
+/// 避免链接过期后仍能从缓存获取。
+pub async fn require_signature(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let site = state
+        .hosts
+        .resolve_request(req.headers(), req.uri())
+        .config
+        .clone();
+    let config = &site.signed_urls;
+    if config.paths.is_empty() {
+        return next.run(req).await;
+    }
+    let path = match normalize_path(req.uri().path(), site.path_case) {
+        Ok(path) => path,
+        Err(err) => return err.into_response(),
+    };
+    if !config.is_protected(&path, site.path_case) {
+        return next.run(req).await;
+    }
+
+    let now = unix_now();
+    let expires = match verify(config, &path, req.uri(), now) {
+        Ok(expires) => expires,
+        Err(rejected) => {
+            tracing::debug!("Rejected signed URL for /{path}: {rejected}");
+            let mut response = builtin_error_page(StatusCode::FORBIDDEN);
+            response
+                .headers_mut()
+                .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+            return response;
+        }
+    };
+    let mut response = next.run(req).await;
+    limit_cache_lifetime(&mut response, expires.saturating_sub(now));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CacheConfig, CacheRule, PathPattern, SigningKey, SiteConfig};
+    use std::sync::Arc;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn config() -> SignedUrlConfig {
+        SignedUrlConfig {
+            paths: vec!["/private".to_string()],
+            keys: vec![
+                SigningKey {
+                    id: "2026-10".to_string(),
+                    secret: "new-secret-0123456789".to_string(),
+                },
+                SigningKey {
+                    id: "2026-04".to_string(),
+                    secret: "old-secret-0123456789".to_string(),
+                },
+            ],
+            clock_skew: 30,
+        }
+    }
+
+    fn verify_url(config: &SignedUrlConfig, url: &str, now: u64) -> Result<(), Rejected> {
+        let uri: Uri = url.parse().unwrap();
+        let path = normalize_path(uri.path(), PathCase::Preserve).unwrap();
+        verify(config, &path, &uri, now).map(|_| ())
+    }
+
+    /// 测试生成和校验签名
+    ///
+    /// 验证：
+    /// - 默认使用第一个密钥签名，签名路径经过规范化和百分号编码
+    /// - 轮换后的旧密钥签名仍然有效，未知密钥被拒绝
+    /// - 修改路径、过期时间或签名后校验失败，缺少参数时被拒绝
+    /// - 过期时间在允许的时钟偏差内仍然有效，超出后被拒绝
+    #[test]
+    fn test_sign_and_verify() {
+        let config = config();
+        let expires = NOW + 600;
+
+        let url = sign(
+            &config,
+            "/private//报告.pdf",
+            PathCase::Preserve,
+            expires,
+            None,
+        )
+        .unwrap();
+        assert!(url.starts_with(&format!(
+            "/private/%E6%8A%A5%E5%91%8A.pdf?expires={expires}&kid=2026-10&signature="
+        )));
+        assert_eq!(verify_url(&config, &url, NOW), Ok(()));
+
+        let old = sign(
+            &config,
+            "/private/a.txt",
+            PathCase::Preserve,
+            expires,
+            Some("2026-04"),
+        )
+        .unwrap();
+        assert_eq!(verify_url(&config, &old, NOW), Ok(()));
+        assert_eq!(
+            sign(&config, "/a", PathCase::Preserve, expires, Some("gone")),
+            Err(SignError::UnknownKey("gone".to_string()))
+        );
+        assert_eq!(
+            sign(
+                &SignedUrlConfig::default(),
+                "/a",
+                PathCase::Preserve,
+                expires,
+                None
+            ),
+            Err(SignError::NoKeys)
+        );
+
+        let tampered_path = old.replace("a.txt", "b.txt");
+        assert_eq!(
+            verify_url(&config, &tampered_path, NOW),
+            Err(Rejected::Mismatch)
+        );
+        let tampered_expires = old.replace(&expires.to_string(), &(expires + 1).to_string());
+        assert_eq!(
+            verify_url(&config, &tampered_expires, NOW),
+            Err(Rejected::Mismatch)
+        );
+        let tampered_signature = format!("{old}A");
+        assert_eq!(
+            verify_url(&config, &tampered_signature, NOW),
+            Err(Rejected::Mismatch)
+        );
+        let unknown_key = old.replace("kid=2026-04", "kid=2025-01");
+        assert_eq!(
+            verify_url(&config, &unknown_key, NOW),
+            Err(Rejected::UnknownKey("2025-01".to_string()))
+        );
+        assert_eq!(
+            verify_url(&config, "/private/a.txt", NOW),
+            Err(Rejected::Missing)
+        );
+        assert_eq!(
+            verify_url(
+                &config,
+                "/private/a.txt?expires=soon&kid=2026-10&signature=x",
+                NOW
+            ),
+            Err(Rejected::Missing)
+        );
+
+        assert_eq!(verify_url(&config, &url, expires + 30), Ok(()));
+        assert_eq!(
+            verify_url(&config, &url, expires + 31),
+            Err(Rejected::Expired(expires))
+        );
+    }
+
+    /// 测试签名 URL 中间件挂载到应用路由
+    ///
+    /// 验证：
+    /// - 私有路径下没有签名或签名无效时返回 403，不读取文件
+    /// - 签名有效时返回文件，`Cache-Control` 中的 `public` 被替换为 `private`
+    /// - 同一文件的编码形式共用签名，其他路径不需要签名
+    #[tokio::test]
+    async fn test_require_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("private")).unwrap();
+        std::fs::write(dir.path().join("private/report.txt"), "report").unwrap();
+        std::fs::write(dir.path().join("public.txt"), "public").unwrap();
+        let storage = Arc::new(crate::storage::FsStorage::new(dir.path(), "www").unwrap());
+
+        let site = SiteConfig {
+            signed_urls: config(),
+            ..SiteConfig::default()
+        };
+        let state = AppState::with_site(storage.clone(), reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+
+        server
+            .get("/public.txt")
+            .await
+            .assert_status(StatusCode::OK);
+        let resp = server.get("/private/report.txt").await;
+        resp.assert_status(StatusCode::FORBIDDEN);
+        assert_eq!(resp.header(CACHE_CONTROL), "no-store");
+
+        let url = sign(
+            &config(),
+            "/private/report.txt",
+            PathCase::Preserve,
+            unix_now() + 60,
+            None,
+        )
+        .unwrap();
+        let resp = server.get(&url).await;
+        resp.assert_status(StatusCode::OK);
+        assert_eq!(resp.text(), "report");
+        let max_age = |resp: &axum_test::TestResponse| -> u64 {
+            let value = resp.header(CACHE_CONTROL);
+            let value = value.to_str().unwrap();
+            assert!(value.starts_with("private, max-age="), "{value}");
+            assert!(!value.contains("public") && !value.contains("s-maxage"));
+            value["private, max-age=".len()..]
+                .split(',')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+        assert!(max_age(&resp) <= 60);
+        assert!(
+            resp.header(CACHE_CONTROL)
+                .to_str()
+                .unwrap()
+                .ends_with("must-revalidate")
+        );
+
+        let encoded = url.replace("report.txt", "%72eport.txt");
+        server.get(&encoded).await.assert_status(StatusCode::OK);
+
+        let expired = sign(
+            &config(),
+            "/private/report.txt",
+            PathCase::Preserve,
+            unix_now() - 3600,
+            None,
+        )
+        .unwrap();
+        server
+            .get(&expired)
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        let site = SiteConfig {
+            signed_urls: config(),
+            cache: CacheConfig {
+                rules: vec![CacheRule {
+                    glob: Some(PathPattern::new("private/**").unwrap()),
+                    regex: None,
+                    cache_control: "public, max-age=31536000, s-maxage=31536000, immutable"
+                        .to_string(),
+                }],
+                ..CacheConfig::default()
+            },
+            ..SiteConfig::default()
+        };
+        let state = AppState::with_site(storage, reqwest::Client::new(), site);
+        let server = axum_test::TestServer::new(crate::router(state, Vec::new())).unwrap();
+        let resp = server.get(&url).await;
+        resp.assert_status(StatusCode::OK);
+        assert!(max_age(&resp) <= 60);
+        assert!(
+            resp.header(CACHE_CONTROL)
+                .to_str()
+                .unwrap()
+                .ends_with("immutable")
+        );
+    }
+
+    /// 测试签名 URL 中间件拒绝无法规范化的路径
+    ///
+    /// 验证：
+    /// - 私有路径带有无效的 UTF-8 编码时返回 400，请求不会交给后续处理器（如反向代理）
+    #[tokio::test]
+    async fn test_require_signature_rejects_invalid_path() {
+        let site = SiteConfig {
+            signed_urls: config(),
+            ..SiteConfig::default()
+        };
+        let storage = Arc::new(crate::storage::MockStorage::new());
+        let state = AppState::with_site(storage, reqwest::Client::new(), site);
+        let router = axum::Router::new()
+            .fallback(|| async { "unprotected" })
+            .layer(axum::middleware::from_fn_with_state(
+                state,
+                require_signature,
+            ));
+        let server = axum_test::TestServer::new(router).unwrap();
+
+        let resp = server.get("/private/report.txt%FF").await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        assert_ne!(resp.text(), "unprotected");
+    }
+
+    /// 测试签名响应的缓存时间限制
+    ///
+    /// 验证：
+    /// - `max-age` 超过剩余有效期时被缩短，较短的 `max-age` 保持不变
+    /// - 没有 `max-age` 时按剩余有效期添加
+    /// - `public` 和 `s-maxage` 被去掉，`no-store` 的响应不添加 `max-age`
+    #[test]
+    fn test_limit_cache_lifetime() {
+        let limited = |value: &str, remaining| {
+            let mut response = Response::new(axum::body::Body::empty());
+            response
+                .headers_mut()
+                .insert(CACHE_CONTROL, HeaderValue::from_str(value).unwrap());
+            limit_cache_lifetime(&mut response, remaining);
+            response.headers()[CACHE_CONTROL]
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            limited(
+                "public, max-age=31536000, s-maxage=31536000, immutable",
+                300
+            ),
+            "private, max-age=300, immutable"
+        );
+        assert_eq!(limited("max-age=60", 300), "private, max-age=60");
+        assert_eq!(limited("no-cache", 300), "private, max-age=300, no-cache");
+        assert_eq!(limited("public, no-store", 300), "private, no-store");
+        assert_eq!(limited("max-age=60", 0), "private, max-age=0");
+    }
+}
@@ -0,0 +1,426 @@
+//! 虚拟主机
+//!
+//! 根据请求的 Host 头选择站点（存储后端 + 站点配置），使一个进程可以同时服务多个域名。
+//! 匹配顺序：精确域名 > 通配符域名（后缀越长越优先）> 默认站点。
+
+use crate::config::{CompressionConfig, Config, ConfigError, SiteConfig, StorageConfig};
+use crate::storage::{CacheOptions, CacheStats, CachedStorage, FsStorage, S3Storage, Storage};
+use axum::http::HeaderMap;
+use axum::http::header::HOST;
+use axum::http::uri::Uri;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 站点：存储后端和对应的站点配置
+#[derive(Clone)]
+pub struct Site {
+    pub storage: Arc<dyn Storage>,
+    pub config: Arc<SiteConfig>,
+}
+
+impl Site {
+    pub fn new(storage: Arc<dyn Storage>, config: SiteConfig) -> Self {
+        Self {
+            storage,
+            config: Arc::new(config),
+        }
+    }
+}
+
+/// 主机名匹配表：精确域名优先，其次是后缀最长的通配符域名
+///
+/// 虚拟主机表和 `Config::site_for_host` 共用，保证两者的匹配规则一致。
+#[derive(Clone)]
+pub struct HostMatcher<T> {
+    exact: HashMap<String, T>,
+    /// 通配符域名，保存为 `.suffix` 形式，按后缀长度降序排列
+    wildcard: Vec<(String, T)>,
+}
+
+impl<T> HostMatcher<T> {
+    pub fn new() -> Self {
+        Self {
+            exact: HashMap::new(),
+            wildcard: Vec::new(),
+        }
+    }
+
+    /// 添加主机名
+    ///
+    /// `name` 为精确域名或 `*.example.com` 形式的通配符，通配符匹配任意层级的子域名，
+    /// 但不匹配 `example.com` 本身。
+    pub fn add(&mut self, name: &str, value: T) {
+        let name = name.to_ascii_lowercase();
+        match name.strip_prefix('*') {
+            Some(suffix) => {
+                self.wildcard.push((suffix.to_string(), value));
+                self.wildcard
+                    .sort_by_key(|(suffix, _)| std::cmp::Reverse(suffix.len()));
+            }
+            None => {
+                self.exact.insert(name, value);
+            }
+        }
+    }
+
+    /// 查找主机名（可带端口）对应的值
+    ///
+    /// 主机名中的端口和末尾的点会被忽略，比较不区分大小写。
+    pub fn get(&self, host: &str) -> Option<&T> {
+        let host = normalize_host(host);
+        if host.is_empty() {
+            return None;
+        }
+        if let Some(value) = self.exact.get(&host) {
+            return Some(value);
+        }
+        self.wildcard
+            .iter()
+            .find(|(suffix, _)| host.len() > suffix.len() && host.ends_with(suffix.as_str()))
+            .map(|(_, value)| value)
+    }
+}
+
+impl<T> Default for HostMatcher<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 虚拟主机表
+#[derive(Clone)]
+pub struct VirtualHosts {
+    default: Site,
+    hosts: HostMatcher<Site>,
+    /// 各存储桶带缓存的存储实例（按存储桶名称），用于读取缓存命中统计
+    caches: Vec<(String, Arc<CachedStorage>)>,
+    /// 可压缩的 MIME 类型白名单（`compression.mime_types`），用于选择预压缩文件
+    pub compressible_types: Vec<String>,
+}
+
+impl VirtualHosts {
+    /// 创建只有默认站点的虚拟主机表
+    pub fn new(default: Site) -> Self {
+        Self {
+            default,
+            hosts: HostMatcher::new(),
+            caches: Vec::new(),
+            compressible_types: CompressionConfig::default().mime_types,
+        }
+    }
+
+    /// 添加虚拟主机，`name` 的格式见 `HostMatcher::add`
+    pub fn add(&mut self, name: &str, site: Site) {
+        self.hosts.add(name, site);
+    }
+
+    /// 根据主机名选择站点，未匹配时返回默认站点
+    ///
+    /// 主机名中的端口和末尾的点会被忽略，比较不区分大小写。
+    pub fn resolve(&self, host: Option<&str>) -> &Site {
+        host.and_then(|host| self.hosts.get(host))
+            .unwrap_or(&self.default)
+    }
+
+    /// 根据请求选择站点
+    ///
+    /// 优先使用 Host 头，没有时使用请求 URI 中的 authority（HTTP/2）。
+    pub fn resolve_request(&self, headers: &HeaderMap, uri: &Uri) -> &Site {
+        let host = headers
+            .get(HOST)
+            .and_then(|v| v.to_str().ok())
+            .or_else(|| uri.authority().map(|a| a.as_str()));
+        self.resolve(host)
+    }
+
+    /// 根据配置创建虚拟主机表和各站点的存储后端
+    ///
+    /// 使用同一个存储桶的站点共享同一个带缓存的存储实例。
+    pub async fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut factory = StorageFactory::new((&config.storage.cache).into());
+
+        let default_storage = factory.build(&config.storage, &config.site).await?;
+        let mut hosts = Self::new(Site::new(default_storage.clone(), config.site.clone()));
+
+        for host in &config.hosts {
+            let storage = match (&host.bucket, &host.root) {
+                (None, None) => match &config.storage.root {
+                    // 本地目录存储按前缀映射存储键，需要按站点的前缀重新创建
+                    Some(root) => factory.fs(root, &host.site.prefix)?,
+                    None => default_storage.clone(),
+                },
+                (bucket, root) => {
+                    let storage_config = StorageConfig {
+                        bucket: bucket.clone(),
+                        root: root.clone(),
+                        ..StorageConfig::default()
+                    };
+                    factory.build(&storage_config, &host.site).await?
+                }
+            };
+            let site = Site::new(storage, host.site.clone());
+            for name in &host.names {
+                tracing::info!("Serving host {} with prefix '{}'", name, host.site.prefix);
+                hosts.add(name, site.clone());
+            }
+        }
+
+        hosts.caches = factory.caches;
+        hosts.compressible_types = config.compression.mime_types.clone();
+        Ok(hosts)
+    }
+
+    /// 各存储桶的缓存命中统计（按存储桶名称），本地目录存储不使用缓存
+    pub fn cache_stats(&self) -> Vec<(&str, CacheStats)> {
+        self.caches
+            .iter()
+            .map(|(bucket, storage)| (bucket.as_str(), storage.stats()))
+            .collect()
+    }
+
+    /// 按固定间隔以 info 级别记录各存储桶的缓存命中统计
+    pub async fn log_cache_stats(self: Arc<Self>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        // 第一次 tick 立即完成，此时还没有统计数据
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            for (bucket, stats) in self.cache_stats() {
+                tracing::info!(
+                    "Storage cache for bucket {}: exists {} hits / {} misses, presign {} hits / {} misses",
+                    bucket,
+                    stats.exists_hits,
+                    stats.exists_misses,
+                    stats.presign_hits,
+                    stats.presign_misses
+                );
+            }
+        }
+    }
+}
+
+/// 去掉主机名中的端口和末尾的点，并转换为小写
+fn normalize_host(host: &str) -> String {
+    let host = host.trim();
+    let host = if host.starts_with('[') {
+        // IPv6 地址，如 [::1]:8080
+        host.split_once(']').map_or(host, |(addr, _)| addr)
+    } else {
+        host.rsplit_once(':').map_or(host, |(name, _)| name)
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// 存储后端工厂，按存储桶复用存储实例，并延迟初始化 S3 客户端
+struct StorageFactory {
+    cache: CacheOptions,
+    s3_client: Option<Arc<aws_sdk_s3::Client>>,
+    buckets: HashMap<String, Arc<dyn Storage>>,
+    caches: Vec<(String, Arc<CachedStorage>)>,
+}
+
+impl StorageFactory {
+    fn new(cache: CacheOptions) -> Self {
+        Self {
+            cache,
+            s3_client: None,
+            buckets: HashMap::new(),
+            caches: Vec::new(),
+        }
+    }
+
+    async fn build(
+        &mut self,
+        storage: &StorageConfig,
+        site: &SiteConfig,
+    ) -> Result<Arc<dyn Storage>, ConfigError> {
+        match (&storage.root, &storage.bucket) {
+            (Some(root), _) => self.fs(root, &site.prefix),
+            (None, Some(bucket_name)) => Ok(self.s3(bucket_name).await),
+            (None, None) => Err(ConfigError::Invalid(
+                "either a bucket or a local root must be set".to_string(),
+            )),
+        }
+    }
+
+    fn fs(&self, root: &Path, prefix: &str) -> Result<Arc<dyn Storage>, ConfigError> {
+        let storage = FsStorage::new(root, prefix)
+            .map_err(|e| ConfigError::Invalid(format!("invalid root '{}': {e}", root.display())))?;
+        tracing::info!("Serving files from local directory {}", root.display());
+        Ok(Arc::new(storage))
+    }
+
+    async fn s3(&mut self, bucket_name: &str) -> Arc<dyn Storage> {
+        if let Some(storage) = self.buckets.get(bucket_name) {
+            return storage.clone();
+        }
+
+        let s3_client = match &self.s3_client {
+            Some(client) => client.clone(),
+            None => {
+                // 初始化 S3 客户端
+                let s3_config = aws_config::load_from_env().await;
+                let client = Arc::new(aws_sdk_s3::Client::new(&s3_config));
+                self.s3_client = Some(client.clone());
+                client
+            }
+        };
+
+        let storage = S3Storage::new(s3_client, bucket_name.to_string());
+
+        // 缓存存在性检查和预签名 URL，减少 SPA 回退时的 HEAD 请求
+        let cached = Arc::new(CachedStorage::new(Arc::new(storage), self.cache.clone()));
+        self.caches.push((bucket_name.to_string(), cached.clone()));
+        let storage: Arc<dyn Storage> = cached;
+        self.buckets
+            .insert(bucket_name.to_string(), storage.clone());
+        storage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VirtualHostConfig;
+    use crate::storage::MockStorage;
+
+    fn site(prefix: &str) -> Site {
+        Site::new(
+            Arc::new(MockStorage::new()),
+            SiteConfig {
+                prefix: prefix.to_string(),
+                ..SiteConfig::default()
+            },
+        )
+    }
+
+    fn prefix_of(hosts: &VirtualHosts, host: Option<&str>) -> String {
+        hosts.resolve(host).config.prefix.clone()
+    }
+
+    /// 测试虚拟主机匹配
+    ///
+    /// 验证：
+    /// - 精确域名优先于通配符
+    /// - 通配符匹配任意层级子域名，后缀更长的通配符优先，但不匹配裸域名
+    /// - 端口、大小写和末尾的点被忽略
+    /// - 未匹配或没有 Host 头时使用默认站点
+    #[test]
+    fn test_resolve() {
+        let mut hosts = VirtualHosts::new(site("default"));
+        hosts.add("example.com", site("example"));
+        hosts.add("*.example.com", site("example-sub"));
+        hosts.add("*.preview.example.com", site("preview"));
+        hosts.add("pr-1.preview.example.com", site("pr-1"));
+
+        assert_eq!(prefix_of(&hosts, Some("example.com")), "example");
+        assert_eq!(prefix_of(&hosts, Some("EXAMPLE.com:8080")), "example");
+        assert_eq!(prefix_of(&hosts, Some("example.com.")), "example");
+        assert_eq!(prefix_of(&hosts, Some("www.example.com")), "example-sub");
+        assert_eq!(prefix_of(&hosts, Some("a.b.example.com")), "example-sub");
+        assert_eq!(
+            prefix_of(&hosts, Some("pr-2.preview.example.com")),
+            "preview"
+        );
+        assert_eq!(prefix_of(&hosts, Some("pr-1.preview.example.com")), "pr-1");
+        assert_eq!(
+            prefix_of(&hosts, Some("preview.example.com")),
+            "example-sub"
+        );
+        assert_eq!(prefix_of(&hosts, Some("other.com")), "default");
+        assert_eq!(prefix_of(&hosts, Some("[::1]:3000")), "default");
+        assert_eq!(prefix_of(&hosts, None), "default");
+    }
+
+    /// 测试缓存命中统计
+    ///
+    /// 验证：
+    /// - 通过站点存储访问的结果计入对应存储桶的统计
+    /// - 没有带缓存的存储时统计为空
+    #[tokio::test]
+    async fn test_cache_stats() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .times(1)
+            .returning(|_| Ok(true));
+        let cached = Arc::new(CachedStorage::new(
+            Arc::new(mock_storage),
+            CacheOptions::default(),
+        ));
+
+        let mut hosts = VirtualHosts::new(Site::new(cached.clone(), SiteConfig::default()));
+        hosts.caches.push(("main".to_string(), cached));
+
+        let storage = &hosts.resolve(None).storage;
+        storage.check_key_exists("www/index.html").await.unwrap();
+        storage.check_key_exists("www/index.html").await.unwrap();
+
+        let stats = hosts.cache_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].0, "main");
+        assert_eq!(stats[0].1.exists_hits, 1);
+        assert_eq!(stats[0].1.exists_misses, 1);
+
+        assert!(VirtualHosts::new(site("www")).cache_stats().is_empty());
+    }
+
+    /// 测试从配置创建虚拟主机表
+    ///
+    /// 验证：
+    /// - 未单独配置存储的虚拟主机使用默认本地目录，并按自己的前缀映射
+    /// - 单独配置本地目录的虚拟主机使用自己的目录
+    #[tokio::test]
+    async fn test_from_config() {
+        let shared = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(shared.path().join("a")).unwrap();
+        std::fs::write(shared.path().join("a/index.html"), "a").unwrap();
+        std::fs::write(shared.path().join("index.html"), "default").unwrap();
+        let own = tempfile::tempdir().unwrap();
+        std::fs::write(own.path().join("index.html"), "b").unwrap();
+
+        let mut config = Config::default();
+        config.storage.root = Some(shared.path().to_path_buf());
+        config.site.prefix = String::new();
+        config.hosts = vec![
+            VirtualHostConfig {
+                names: vec!["a.example.com".to_string()],
+                site: SiteConfig {
+                    prefix: "a".to_string(),
+                    ..SiteConfig::default()
+                },
+                ..VirtualHostConfig::default()
+            },
+            VirtualHostConfig {
+                names: vec!["*.b.example.com".to_string()],
+                root: Some(own.path().to_path_buf()),
+                site: SiteConfig {
+                    prefix: "b".to_string(),
+                    ..SiteConfig::default()
+                },
+                ..VirtualHostConfig::default()
+            },
+        ];
+        config.validate().unwrap();
+
+        let hosts = VirtualHosts::from_config(&config).await.unwrap();
+
+        let a = hosts.resolve(Some("a.example.com"));
+        assert!(a.storage.check_key_exists("a/index.html").await.unwrap());
+
+        let b = hosts.resolve(Some("x.b.example.com"));
+        assert_eq!(b.config.prefix, "b");
+        assert!(b.storage.check_key_exists("b/index.html").await.unwrap());
+
+        let default = hosts.resolve(Some("unknown.com"));
+        assert!(
+            default
+                .storage
+                .check_key_exists("index.html")
+                .await
+                .unwrap()
+        );
+    }
+}
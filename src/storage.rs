@@ -1,6 +1,128 @@
+pub mod cache;
+pub mod fs;
+
+pub use cache::{CacheOptions, CacheStats, CachedStorage};
+pub use fs::FsStorage;
+
 use crate::error::AppError;
 use async_trait::async_trait;
 use aws_sdk_s3::error::ProvideErrorMetadata;
+use axum::http::StatusCode;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use tokio_util::io::ReaderStream;
+
+/// 预签名 URL 的默认有效期（1 小时）
+pub const DEFAULT_PRESIGNED_URL_EXPIRY: Duration = Duration::from_secs(3600);
+
+/// S3 预签名 URL 的最长有效期（7 天）
+pub const MAX_PRESIGNED_URL_EXPIRY: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// 预签名选项
+///
+/// `response_*` 字段通过 S3 的 `response-*` 查询参数覆盖下载时的响应头。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PresignOptions {
+    /// 有效期，不能超过 7 天
+    pub expires_in: Duration,
+    /// 覆盖响应的 Content-Type
+    pub response_content_type: Option<String>,
+    /// 覆盖响应的 Cache-Control
+    pub response_cache_control: Option<String>,
+    /// 覆盖响应的 Content-Disposition
+    pub response_content_disposition: Option<String>,
+}
+
+impl Default for PresignOptions {
+    fn default() -> Self {
+        Self {
+            expires_in: DEFAULT_PRESIGNED_URL_EXPIRY,
+            response_content_type: None,
+            response_cache_control: None,
+            response_content_disposition: None,
+        }
+    }
+}
+
+impl PresignOptions {
+    /// 校验有效期：必须大于 0 且不超过 S3 的 7 天上限
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.expires_in.is_zero() || self.expires_in > MAX_PRESIGNED_URL_EXPIRY {
+            return Err(AppError::InvalidPresign(format!(
+                "expiry must be between 1 second and {} seconds, got {:?}",
+                MAX_PRESIGNED_URL_EXPIRY.as_secs(),
+                self.expires_in
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 对象元数据
+///
+/// 由 `Storage::head` 和 `Storage::get_object` 返回，用于条件请求、Range 校验、
+/// 预压缩文件和按对象设置的缓存头等无需下载对象内容的场景
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectMeta {
+    /// 对象大小（字节）
+    pub content_length: u64,
+    /// 对象的 ETag（包含引号，如 `"abc123"`）
+    pub etag: Option<String>,
+    /// 最后修改时间
+    pub last_modified: Option<SystemTime>,
+    /// 存储中记录的 Content-Type
+    pub content_type: Option<String>,
+    /// 存储中记录的 Content-Encoding（如以 gzip 存储的对象）
+    pub content_encoding: Option<String>,
+    /// 存储中记录的 Cache-Control（上传时设置）
+    pub cache_control: Option<String>,
+    /// 用户自定义元数据（S3 的 `x-amz-meta-*`，键不含前缀）
+    pub metadata: HashMap<String, String>,
+}
+
+/// 读取对象的参数
+///
+/// 条件参数与 HTTP 条件请求头的语义相同，不满足时返回 304 或 412 而不返回内容。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GetObjectOptions {
+    /// 字节区间（闭区间），调用方需保证区间位于对象大小之内
+    pub range: Option<(u64, u64)>,
+    /// ETag 列表，不匹配时返回 412
+    pub if_match: Option<String>,
+    /// ETag 列表，匹配时返回 304
+    pub if_none_match: Option<String>,
+    /// 对象在此时间之后没有修改时返回 304
+    pub if_modified_since: Option<SystemTime>,
+    /// 对象在此时间之后有修改时返回 412
+    pub if_unmodified_since: Option<SystemTime>,
+}
+
+/// 读取对象的结果
+pub struct GetObjectOutput {
+    /// 200（完整内容）、206（区间内容）、304、412 或 404
+    pub status: StatusCode,
+    /// 对象元数据，`content_length` 为整个对象的大小；没有返回内容时为默认值
+    pub meta: ObjectMeta,
+    /// 返回内容对应的区间（闭区间），完整内容时为 `None`
+    pub range: Option<(u64, u64)>,
+    /// 对象内容
+    pub body: BoxStream<'static, Result<Bytes, AppError>>,
+}
+
+impl GetObjectOutput {
+    /// 没有返回内容的结果（304、412、404）
+    pub fn empty(status: StatusCode) -> Self {
+        Self {
+            status,
+            meta: ObjectMeta::default(),
+            range: None,
+            body: stream::empty().boxed(),
+        }
+    }
+}
 
 /// 存储抽象接口
 ///
@@ -8,12 +130,65 @@ use aws_sdk_s3::error::ProvideErrorMetadata;
 #[mockall::automock]
 #[async_trait]
 pub trait Storage: Send + Sync + 'static {
-    /// 生成预签名 URL
-    async fn get_presigned_url(&self, key: &str) -> Result<String, AppError>;
+    /// 按指定的有效期生成预签名 URL，不覆盖响应头
+    async fn get_presigned_url(
+        &self,
+        key: &str,
+        expires_in: Duration,
+    ) -> Result<String, AppError> {
+        self.get_presigned_url_with(
+            key,
+            &PresignOptions {
+                expires_in,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// 按指定的有效期和响应头覆盖生成预签名 URL
+    async fn get_presigned_url_with(
+        &self,
+        key: &str,
+        options: &PresignOptions,
+    ) -> Result<String, AppError>;
+
+    /// 获取对象元数据
+    /// 对象不存在时返回 `Ok(None)`
+    async fn head(&self, key: &str) -> Result<Option<ObjectMeta>, AppError>;
 
     /// 检查对象是否存在
-    /// 返回 KeyStatus，不存在时返回 NotFound（不区分 S3 错误，避免 Result 开销）
-    async fn check_key_exists(&self, key: &str) -> Result<bool, AppError>;
+    /// 默认基于 `head` 实现，不存在时返回 `Ok(false)`
+    async fn check_key_exists(&self, key: &str) -> Result<bool, AppError> {
+        Ok(self.head(key).await?.is_some())
+    }
+
+    /// 读取对象内容和元数据
+    /// 对象不存在时返回状态码为 404 的结果，而不是错误
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<GetObjectOutput, AppError>;
+}
+
+/// 从 HeadObject / GetObject 的输出构造对象元数据
+///
+/// 两个 SDK 输出类型没有共同的 trait，字段访问方法同名，用宏避免重复。
+macro_rules! s3_object_meta {
+    ($output:expr, $content_length:expr) => {
+        ObjectMeta {
+            content_length: $content_length,
+            etag: $output.e_tag().map(str::to_string),
+            last_modified: $output
+                .last_modified()
+                .and_then(|t| SystemTime::try_from(*t).ok()),
+            content_type: $output.content_type().map(str::to_string),
+            content_encoding: $output.content_encoding().map(str::to_string),
+            cache_control: $output.cache_control().map(str::to_string),
+            metadata: $output.metadata().cloned().unwrap_or_default(),
+        }
+    };
 }
 
 /// S3 存储实现
@@ -27,21 +202,41 @@ impl S3Storage {
     pub fn new(client: std::sync::Arc<aws_sdk_s3::Client>, bucket_name: String) -> Self {
         Self { client, bucket_name }
     }
+
+    /// 将 S3 的服务端错误转换为应用错误
+    fn service_error(&self, error: &impl ProvideErrorMetadata) -> AppError {
+        match error.code() {
+            Some("AccessDenied") => AppError::S3("Access denied".to_string()),
+            Some("NoSuchBucket") => AppError::S3(format!("Bucket '{}' not found", self.bucket_name)),
+            _ => AppError::S3(format!(
+                "S3 error: {} - {}",
+                error.code().unwrap_or("Unknown"),
+                error.message().unwrap_or("No message")
+            )),
+        }
+    }
 }
 
 #[async_trait]
 impl Storage for S3Storage {
-    async fn get_presigned_url(&self, key: &str) -> Result<String, AppError> {
-        let presigning_config = aws_sdk_s3::presigning::PresigningConfig::expires_in(
-            std::time::Duration::from_secs(3600),
-        )
-        .map_err(|e| AppError::S3(e.to_string()))?;
+    async fn get_presigned_url_with(
+        &self,
+        key: &str,
+        options: &PresignOptions,
+    ) -> Result<String, AppError> {
+        options.validate()?;
+        let presigning_config =
+            aws_sdk_s3::presigning::PresigningConfig::expires_in(options.expires_in)
+                .map_err(|e| AppError::S3(e.to_string()))?;
 
         let presigned_request = self
             .client
             .get_object()
             .bucket(&self.bucket_name)
             .key(key)
+            .set_response_content_type(options.response_content_type.clone())
+            .set_response_cache_control(options.response_cache_control.clone())
+            .set_response_content_disposition(options.response_content_disposition.clone())
             .presigned(presigning_config)
             .await
             .map_err(|e| AppError::S3(e.to_string()))?;
@@ -49,7 +244,7 @@ impl Storage for S3Storage {
         Ok(presigned_request.uri().to_string())
     }
 
-    async fn check_key_exists(&self, key: &str) -> Result<bool, AppError> {
+    async fn head(&self, key: &str) -> Result<Option<ObjectMeta>, AppError> {
         let result = self
             .client
             .head_object()
@@ -58,29 +253,362 @@ impl Storage for S3Storage {
             .send()
             .await;
 
-        let err = match result {
-            Ok(_) => return Ok(true),
-            Err(e) => e,
+        let output = match result {
+            Ok(output) => output,
+            Err(err) => {
+                let Some(head_error) = err.as_service_error() else {
+                    return Err(AppError::S3(format!("Request failed: {}", err)));
+                };
+                if head_error.is_not_found() {
+                    return Ok(None);
+                }
+                return Err(self.service_error(head_error));
+            }
+        };
+
+        let content_length = output.content_length().unwrap_or_default().max(0) as u64;
+        Ok(Some(s3_object_meta!(output, content_length)))
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<GetObjectOutput, AppError> {
+        let result = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .set_range(options.range.map(|(start, end)| format!("bytes={start}-{end}")))
+            .set_if_match(options.if_match.clone())
+            .set_if_none_match(options.if_none_match.clone())
+            .set_if_modified_since(options.if_modified_since.map(Into::into))
+            .set_if_unmodified_since(options.if_unmodified_since.map(Into::into))
+            .send()
+            .await;
+
+        let output = match result {
+            Ok(output) => output,
+            Err(err) => {
+                // 304 和 412 没有响应体，SDK 以错误的形式返回
+                match err.raw_response().map(|r| r.status().as_u16()) {
+                    Some(304) => return Ok(GetObjectOutput::empty(StatusCode::NOT_MODIFIED)),
+                    Some(412) => {
+                        return Ok(GetObjectOutput::empty(StatusCode::PRECONDITION_FAILED));
+                    }
+                    _ => {}
+                }
+                let Some(get_error) = err.as_service_error() else {
+                    return Err(AppError::S3(format!("Request failed: {}", err)));
+                };
+                // 只有 NoSuchKey 或没有错误码的 404（SDK 会补上 NotFound）表示对象不存在，
+                // NoSuchBucket 等仍然是错误
+                let is_404 = err.raw_response().is_some_and(|r| r.status().as_u16() == 404);
+                if get_error.is_no_such_key()
+                    || (is_404 && matches!(get_error.code(), None | Some("NotFound")))
+                {
+                    return Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND));
+                }
+                return Err(self.service_error(get_error));
+            }
         };
 
-        let Some(head_error) = err.as_service_error() else {
-            return Err(AppError::S3(format!("Request failed: {}", err)));
+        // 区间请求时 Content-Length 是区间长度，对象大小取自 Content-Range（如 bytes 0-99/1234）
+        let content_range = output.content_range().and_then(parse_content_range);
+        let (status, range, content_length) = match content_range {
+            Some((start, end, size)) => (StatusCode::PARTIAL_CONTENT, Some((start, end)), size),
+            None => (
+                StatusCode::OK,
+                None,
+                output.content_length().unwrap_or_default().max(0) as u64,
+            ),
         };
+        let meta = s3_object_meta!(output, content_length);
+
+        let body = ReaderStream::new(output.body.into_async_read())
+            .map_err(AppError::from)
+            .boxed();
+        Ok(GetObjectOutput {
+            status,
+            meta,
+            range,
+            body,
+        })
+    }
+}
 
-        if head_error.is_not_found() {
-            return Ok(false);
+#[cfg(test)]
+impl GetObjectOutput {
+    /// 构造完整内容的读取结果（测试用），对象大小取响应体长度
+    pub(crate) fn full(meta: ObjectMeta, body: impl Into<Bytes>) -> Self {
+        let body = body.into();
+        Self {
+            status: StatusCode::OK,
+            meta: ObjectMeta {
+                content_length: body.len() as u64,
+                ..meta
+            },
+            range: None,
+            body: stream::iter([Ok(body)]).boxed(),
         }
+    }
 
-        let err = match head_error.code() {
-            Some("AccessDenied") => AppError::S3("Access denied".to_string()),
-            Some("NoSuchBucket") => AppError::S3(format!("Bucket '{}' not found", self.bucket_name)),
-            _ => AppError::S3(format!(
-                "S3 error: {} - {}",
-                head_error.code().unwrap_or("Unknown"),
-                head_error.message().unwrap_or("No message")
-            )),
+    /// 构造区间内容的读取结果（测试用），`content` 为完整的对象内容
+    pub(crate) fn partial(meta: ObjectMeta, range: (u64, u64), content: &'static [u8]) -> Self {
+        let body = Bytes::from_static(&content[range.0 as usize..=range.1 as usize]);
+        Self {
+            status: StatusCode::PARTIAL_CONTENT,
+            meta: ObjectMeta {
+                content_length: content.len() as u64,
+                ..meta
+            },
+            range: Some(range),
+            body: stream::iter([Ok(body)]).boxed(),
+        }
+    }
+}
+
+/// 解析 Content-Range 头值（如 `bytes 0-99/1234`），返回起止位置和对象大小
+fn parse_content_range(value: &str) -> Option<(u64, u64, u64)> {
+    let (range, size) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    Some((start.parse().ok()?, end.parse().ok()?, size.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+
+    fn s3_storage() -> S3Storage {
+        s3_storage_at(None)
+    }
+
+    /// 创建使用静态凭证的 S3 存储，可指定 endpoint（如 wiremock 服务）
+    fn s3_storage_at(endpoint: Option<String>) -> S3Storage {
+        let mut builder = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("AKID", "SECRET", None, None, "test"))
+            .force_path_style(true);
+        builder.set_endpoint_url(endpoint);
+        let config = builder.build();
+        S3Storage::new(
+            std::sync::Arc::new(aws_sdk_s3::Client::from_conf(config)),
+            "bucket".to_string(),
+        )
+    }
+
+    /// 测试预签名选项
+    ///
+    /// 验证：
+    /// - 有效期和 `response-*` 覆盖参数写入预签名 URL
+    /// - 默认有效期为 1 小时
+    /// - `get_presigned_url` 只设置有效期，不带 `response-*` 参数
+    #[tokio::test]
+    async fn test_presigned_url_options() {
+        let storage = s3_storage();
+
+        let url = storage
+            .get_presigned_url_with(
+                "www/app.zip",
+                &PresignOptions {
+                    expires_in: Duration::from_secs(600),
+                    response_content_type: Some("application/zip".to_string()),
+                    response_cache_control: Some("no-store".to_string()),
+                    response_content_disposition: Some("attachment".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(url.contains("/www/app.zip?"), "{url}");
+        assert!(url.contains("X-Amz-Expires=600"), "{url}");
+        assert!(url.contains("response-content-type=application%2Fzip"), "{url}");
+        assert!(url.contains("response-cache-control=no-store"), "{url}");
+        assert!(url.contains("response-content-disposition=attachment"), "{url}");
+
+        let url = storage
+            .get_presigned_url("www/index.html", DEFAULT_PRESIGNED_URL_EXPIRY)
+            .await
+            .unwrap();
+        assert!(url.contains("X-Amz-Expires=3600"), "{url}");
+        assert!(!url.contains("response-"), "{url}");
+    }
+
+    /// 测试预签名有效期的校验
+    ///
+    /// 验证：
+    /// - 0 和超过 7 天的有效期被拒绝，且不会发起预签名
+    /// - 恰好 7 天的有效期被接受
+    #[tokio::test]
+    async fn test_presigned_url_expiry_validation() {
+        let storage = s3_storage();
+        for expires_in in [Duration::ZERO, MAX_PRESIGNED_URL_EXPIRY + Duration::from_secs(1)] {
+            let options = PresignOptions {
+                expires_in,
+                ..PresignOptions::default()
+            };
+            assert!(matches!(
+                storage.get_presigned_url_with("www/a", &options).await,
+                Err(AppError::InvalidPresign(_))
+            ));
+        }
+
+        let options = PresignOptions {
+            expires_in: MAX_PRESIGNED_URL_EXPIRY,
+            ..PresignOptions::default()
         };
+        assert!(storage.get_presigned_url_with("www/a", &options).await.is_ok());
+    }
+
+    /// 测试通过 GetObject 读取对象
+    ///
+    /// 验证：
+    /// - 区间和条件参数作为请求头发送
+    /// - 206 响应的对象大小取自 Content-Range，元数据（包括 Cache-Control 和用户元数据）和内容被正确返回
+    /// - 304、412 和 404（NoSuchKey 或没有错误码）转换为对应状态的空响应，而不是错误
+    /// - 带其他错误码的 404（如 NoSuchBucket）返回 S3 错误
+    #[tokio::test]
+    async fn test_get_object() {
+        use futures::TryStreamExt;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/www/app.js"))
+            .and(header("range", "bytes=0-4"))
+            .and(header("if-match", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 0-4/11")
+                    .insert_header("etag", "\"v1\"")
+                    .insert_header("cache-control", "max-age=60")
+                    .insert_header("x-amz-meta-build", "42")
+                    .set_body_raw("Hello", "application/javascript"),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/www/cached.js"))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/www/changed.js"))
+            .respond_with(ResponseTemplate::new(412))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/www/missing.js"))
+            .respond_with(ResponseTemplate::new(404).set_body_string(
+                "<Error><Code>NoSuchKey</Code><Message>missing</Message></Error>",
+            ))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/www/gone.js"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/www/no-bucket.js"))
+            .respond_with(ResponseTemplate::new(404).set_body_string(
+                "<Error><Code>NoSuchBucket</Code><Message>missing</Message></Error>",
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let storage = s3_storage_at(Some(mock_server.uri()));
+        let options = GetObjectOptions {
+            range: Some((0, 4)),
+            if_match: Some("\"v1\"".to_string()),
+            ..GetObjectOptions::default()
+        };
+        let output = storage.get_object("www/app.js", &options).await.unwrap();
+        assert_eq!(output.status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(output.range, Some((0, 4)));
+        assert_eq!(output.meta.content_length, 11);
+        assert_eq!(output.meta.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            output.meta.content_type.as_deref(),
+            Some("application/javascript")
+        );
+        assert_eq!(output.meta.cache_control.as_deref(), Some("max-age=60"));
+        assert_eq!(output.meta.metadata["build"], "42");
+        let body: Vec<Bytes> = output.body.try_collect().await.unwrap();
+        assert_eq!(body.concat(), b"Hello");
+
+        for (key, status) in [
+            ("www/cached.js", StatusCode::NOT_MODIFIED),
+            ("www/changed.js", StatusCode::PRECONDITION_FAILED),
+            ("www/missing.js", StatusCode::NOT_FOUND),
+            ("www/gone.js", StatusCode::NOT_FOUND),
+        ] {
+            let output = storage
+                .get_object(key, &GetObjectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(output.status, status, "{key}");
+        }
+
+        let result = storage
+            .get_object("www/no-bucket.js", &GetObjectOptions::default())
+            .await;
+        assert!(matches!(result, Err(AppError::S3(_))));
+    }
+
+    /// 测试通过 HeadObject 获取对象元数据
+    ///
+    /// 验证：
+    /// - 返回大小、ETag、修改时间、Content-Type、Content-Encoding、Cache-Control 和用户元数据
+    /// - 对象不存在时返回 `None`，默认的 `check_key_exists` 基于 `head` 返回 false
+    #[tokio::test]
+    async fn test_head() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/bucket/www/app.js"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-length", "11")
+                    .insert_header("etag", "\"v1\"")
+                    .insert_header("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+                    .insert_header("content-type", "application/javascript")
+                    .insert_header("content-encoding", "gzip")
+                    .insert_header("cache-control", "no-cache")
+                    .insert_header("x-amz-meta-commit", "abc123"),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/bucket/www/missing.js"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let storage = s3_storage_at(Some(mock_server.uri()));
+        let meta = storage.head("www/app.js").await.unwrap().unwrap();
+        assert_eq!(
+            meta,
+            ObjectMeta {
+                content_length: 11,
+                etag: Some("\"v1\"".to_string()),
+                last_modified: Some(
+                    httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap()
+                ),
+                content_type: Some("application/javascript".to_string()),
+                content_encoding: Some("gzip".to_string()),
+                cache_control: Some("no-cache".to_string()),
+                metadata: HashMap::from([("commit".to_string(), "abc123".to_string())]),
+            }
+        );
+        assert!(storage.check_key_exists("www/app.js").await.unwrap());
 
-        Err(err)
+        assert_eq!(storage.head("www/missing.js").await.unwrap(), None);
+        assert!(!storage.check_key_exists("www/missing.js").await.unwrap());
     }
 }
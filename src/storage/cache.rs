@@ -0,0 +1,381 @@
+//! 带缓存的存储装饰器
+//!
+//! 使用 `cached` 库的 `TimedSizedCache` 缓存对象存在性检查和预签名 URL，
+//! 减少 SPA 回退查找时对存储后端的重复 HEAD 请求和重定向模式下的重复预签名。
+
+use super::{GetObjectOptions, GetObjectOutput, ObjectMeta, PresignOptions, Storage};
+use crate::error::AppError;
+use async_trait::async_trait;
+use cached::{Cached, TimedSizedCache};
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 缓存配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheOptions {
+    /// 对象存在性检查结果的缓存时间，为 0 时不缓存
+    pub exists_ttl: Duration,
+    /// 预签名 URL 的缓存时间，为 0 时不缓存；实际不超过 URL 有效期的一半
+    pub presign_ttl: Duration,
+    /// 每个缓存最多保存的条目数，为 0 时不缓存
+    pub capacity: usize,
+    /// 是否缓存"对象不存在"的结果
+    pub cache_negative: bool,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            exists_ttl: Duration::from_secs(120),
+            presign_ttl: Duration::from_secs(1800),
+            capacity: 10_000,
+            cache_negative: true,
+        }
+    }
+}
+
+/// 缓存命中统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub exists_hits: u64,
+    pub exists_misses: u64,
+    pub presign_hits: u64,
+    pub presign_misses: u64,
+}
+
+/// 单个 TTL 缓存及其命中计数
+struct TtlCache<K, V> {
+    store: Option<Mutex<TimedSizedCache<K, V>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> TtlCache<K, V> {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        let store = (capacity > 0 && !ttl.is_zero())
+            .then(|| Mutex::new(TimedSizedCache::with_size_and_lifespan(capacity, ttl)));
+        Self {
+            store,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_valid(key, |_| true)
+    }
+
+    /// 获取缓存值，`is_valid` 返回 `false` 的值视为未命中
+    fn get_valid<Q>(&self, key: &Q, is_valid: impl FnOnce(&V) -> bool) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let store = self.store.as_ref()?;
+        let value = store
+            .lock()
+            .unwrap()
+            .cache_get(key)
+            .filter(|value| is_valid(value))
+            .cloned();
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    fn set(&self, key: K, value: V) {
+        if let Some(store) = &self.store {
+            store.lock().unwrap().cache_set(key, value);
+        }
+    }
+}
+
+/// 带缓存的存储实现
+///
+/// 包装任意 `Storage`，对 `check_key_exists` 和 `get_presigned_url_with` 的结果做 TTL 缓存。
+/// 预签名 URL 按对象键和预签名选项缓存，只在剩余有效期不少于一半时复用。
+/// 错误结果不会被缓存；`head` 和 `get_object` 直接透传，保证条件请求使用最新的元数据。
+pub struct CachedStorage {
+    inner: Arc<dyn Storage>,
+    cache_negative: bool,
+    exists: TtlCache<String, bool>,
+    presigned: TtlCache<(String, PresignOptions), (String, Instant)>,
+}
+
+impl CachedStorage {
+    pub fn new(inner: Arc<dyn Storage>, options: CacheOptions) -> Self {
+        Self {
+            inner,
+            cache_negative: options.cache_negative,
+            exists: TtlCache::new(options.capacity, options.exists_ttl),
+            presigned: TtlCache::new(options.capacity, options.presign_ttl),
+        }
+    }
+
+    /// 获取缓存命中统计
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            exists_hits: self.exists.hits.load(Ordering::Relaxed),
+            exists_misses: self.exists.misses.load(Ordering::Relaxed),
+            presign_hits: self.presigned.hits.load(Ordering::Relaxed),
+            presign_misses: self.presigned.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for CachedStorage {
+    async fn get_presigned_url_with(
+        &self,
+        key: &str,
+        options: &PresignOptions,
+    ) -> Result<String, AppError> {
+        let cache_key = (key.to_string(), options.clone());
+        let fresh =
+            |(_, signed_at): &(String, Instant)| signed_at.elapsed() < options.expires_in / 2;
+        if let Some((url, _)) = self.presigned.get_valid(&cache_key, fresh) {
+            return Ok(url);
+        }
+        let signed_at = Instant::now();
+        let url = self.inner.get_presigned_url_with(key, options).await?;
+        self.presigned.set(cache_key, (url.clone(), signed_at));
+        Ok(url)
+    }
+
+    async fn check_key_exists(&self, key: &str) -> Result<bool, AppError> {
+        if let Some(exists) = self.exists.get(key) {
+            return Ok(exists);
+        }
+        let exists = self.inner.check_key_exists(key).await?;
+        if exists || self.cache_negative {
+            self.exists.set(key.to_string(), exists);
+        }
+        Ok(exists)
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectMeta>, AppError> {
+        self.inner.head(key).await
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<GetObjectOutput, AppError> {
+        self.inner.get_object(key, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MockStorage;
+    use mockall::predicate::eq;
+
+    /// 测试存在性检查结果被缓存
+    ///
+    /// 验证：
+    /// - 相同键的重复检查只访问一次底层存储
+    /// - 命中和未命中次数被正确统计
+    #[tokio::test]
+    async fn test_check_key_exists_cached() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .with(eq("www/index.html"))
+            .times(1)
+            .returning(|_| Ok(true));
+
+        let storage = CachedStorage::new(Arc::new(mock_storage), CacheOptions::default());
+
+        assert!(storage.check_key_exists("www/index.html").await.unwrap());
+        assert!(storage.check_key_exists("www/index.html").await.unwrap());
+
+        let stats = storage.stats();
+        assert_eq!(stats.exists_hits, 1);
+        assert_eq!(stats.exists_misses, 1);
+    }
+
+    /// 测试不存在结果的缓存开关
+    ///
+    /// 验证：
+    /// - 开启 cache_negative 时"不存在"结果被缓存
+    /// - 关闭 cache_negative 时每次都访问底层存储
+    #[tokio::test]
+    async fn test_negative_caching() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .times(1)
+            .returning(|_| Ok(false));
+        let storage = CachedStorage::new(Arc::new(mock_storage), CacheOptions::default());
+        assert!(!storage.check_key_exists("www/a/index.html").await.unwrap());
+        assert!(!storage.check_key_exists("www/a/index.html").await.unwrap());
+
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .times(2)
+            .returning(|_| Ok(false));
+        let options = CacheOptions {
+            cache_negative: false,
+            ..CacheOptions::default()
+        };
+        let storage = CachedStorage::new(Arc::new(mock_storage), options);
+        assert!(!storage.check_key_exists("www/a/index.html").await.unwrap());
+        assert!(!storage.check_key_exists("www/a/index.html").await.unwrap());
+    }
+
+    /// 测试预签名 URL 缓存和过期
+    ///
+    /// 验证：
+    /// - TTL 内以相同选项重复获取返回缓存的 URL
+    /// - TTL 过期后重新生成
+    #[tokio::test]
+    async fn test_presigned_url_cached_until_expired() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_get_presigned_url_with()
+            .times(2)
+            .returning(|key, _| Ok(format!("https://s3.example.com/{key}")));
+
+        let options = CacheOptions {
+            presign_ttl: Duration::from_millis(50),
+            ..CacheOptions::default()
+        };
+        let storage = CachedStorage::new(Arc::new(mock_storage), options);
+        let presign = PresignOptions::default();
+
+        storage
+            .get_presigned_url_with("www/app.js", &presign)
+            .await
+            .unwrap();
+        storage
+            .get_presigned_url_with("www/app.js", &presign)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        let url = storage
+            .get_presigned_url_with("www/app.js", &presign)
+            .await
+            .unwrap();
+
+        assert_eq!(url, "https://s3.example.com/www/app.js");
+        assert_eq!(storage.stats().presign_hits, 1);
+        assert_eq!(storage.stats().presign_misses, 2);
+    }
+
+    /// 测试预签名 URL 按选项缓存并遵守有效期
+    ///
+    /// 验证：
+    /// - 有效期或响应头覆盖不同的选项分别缓存
+    /// - 缓存的 URL 剩余有效期不足一半时重新生成，即使缓存 TTL 尚未过期
+    #[tokio::test]
+    async fn test_presigned_url_respects_expiry() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_get_presigned_url_with()
+            .times(4)
+            .returning(|key, options| {
+                Ok(format!(
+                    "https://s3.example.com/{key}?X-Amz-Expires={}",
+                    options.expires_in.as_millis()
+                ))
+            });
+        let storage = CachedStorage::new(Arc::new(mock_storage), CacheOptions::default());
+
+        let short = PresignOptions {
+            expires_in: Duration::from_millis(100),
+            ..PresignOptions::default()
+        };
+        let attachment = PresignOptions {
+            response_content_disposition: Some("attachment".to_string()),
+            ..short.clone()
+        };
+        let url = storage
+            .get_presigned_url_with("www/app.zip", &short)
+            .await
+            .unwrap();
+        assert!(url.ends_with("X-Amz-Expires=100"), "{url}");
+        storage
+            .get_presigned_url_with("www/app.zip", &attachment)
+            .await
+            .unwrap();
+        storage
+            .get_presigned_url_with("www/app.zip", &PresignOptions::default())
+            .await
+            .unwrap();
+        storage
+            .get_presigned_url_with("www/app.zip", &short)
+            .await
+            .unwrap();
+        assert_eq!(storage.stats().presign_hits, 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        storage
+            .get_presigned_url_with("www/app.zip", &short)
+            .await
+            .unwrap();
+        assert_eq!(storage.stats().presign_hits, 1);
+        assert_eq!(storage.stats().presign_misses, 4);
+    }
+
+    /// 测试错误结果不被缓存
+    ///
+    /// 验证：
+    /// - 底层存储返回错误时下一次请求重新访问底层存储
+    #[tokio::test]
+    async fn test_errors_not_cached() {
+        let mut mock_storage = MockStorage::new();
+        let mut seq = mockall::Sequence::new();
+        mock_storage
+            .expect_check_key_exists()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Err(AppError::S3("Access denied".to_string())));
+        mock_storage
+            .expect_check_key_exists()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(true));
+
+        let storage = CachedStorage::new(Arc::new(mock_storage), CacheOptions::default());
+
+        assert!(storage.check_key_exists("www/index.html").await.is_err());
+        assert!(storage.check_key_exists("www/index.html").await.unwrap());
+    }
+
+    /// 测试容量为 0 时禁用缓存
+    ///
+    /// 验证：
+    /// - 每次请求都访问底层存储，且不统计命中
+    #[tokio::test]
+    async fn test_disabled_cache() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage
+            .expect_check_key_exists()
+            .times(2)
+            .returning(|_| Ok(true));
+
+        let options = CacheOptions {
+            capacity: 0,
+            ..CacheOptions::default()
+        };
+        let storage = CachedStorage::new(Arc::new(mock_storage), options);
+
+        storage.check_key_exists("www/index.html").await.unwrap();
+        storage.check_key_exists("www/index.html").await.unwrap();
+        assert_eq!(storage.stats(), CacheStats::default());
+    }
+}
@@ -0,0 +1,371 @@
+//! 本地文件系统存储实现
+//!
+//! 将本地目录（如 `./dist`）作为存储桶使用，便于在没有 S3 / MinIO 的情况下本地开发和测试。
+//! 本地目录没有预签名 URL，重定向模式下直接返回文件内容。
+
+use super::{GetObjectOptions, GetObjectOutput, ObjectMeta, PresignOptions, Storage};
+use crate::handlers::conditional::{Precondition, evaluate_conditions};
+use crate::error::AppError;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use mime_guess::MimeGuess;
+use std::fs::Metadata;
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+/// 本地文件系统存储
+///
+/// 存储键需要以 `prefix/` 开头，去掉前缀后映射到根目录下的相对路径，
+/// 例如前缀为 `www` 时，`www/app.js` 对应 `{root}/app.js`。
+/// 解析后的路径（包括符号链接）必须位于根目录内，否则视为不存在。
+#[derive(Clone)]
+pub struct FsStorage {
+    root: PathBuf,
+    prefix: String,
+}
+
+impl FsStorage {
+    /// 创建本地文件系统存储
+    ///
+    /// 根目录必须存在，会被规范化为绝对路径。
+    pub fn new(root: impl AsRef<Path>, prefix: &str) -> std::io::Result<Self> {
+        let root = std::fs::canonicalize(root)?;
+        if !root.is_dir() {
+            return Err(std::io::Error::new(
+                ErrorKind::NotADirectory,
+                format!("'{}' is not a directory", root.display()),
+            ));
+        }
+        Ok(Self {
+            root,
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+
+    /// 将存储键映射为根目录下的路径（不访问文件系统）
+    ///
+    /// 键不以前缀开头，或包含空段、`.`、`..`、反斜杠、NUL 时返回 `None`。
+    fn resolve(&self, key: &str) -> Option<PathBuf> {
+        let relative = if self.prefix.is_empty() {
+            key
+        } else {
+            key.strip_prefix(&self.prefix)?.strip_prefix('/')?
+        };
+
+        let mut path = self.root.clone();
+        for segment in relative.split('/') {
+            if segment.is_empty()
+                || segment == "."
+                || segment == ".."
+                || segment.contains(['\\', '\0'])
+            {
+                return None;
+            }
+            path.push(segment);
+        }
+        Some(path)
+    }
+
+    /// 查找存储键对应的普通文件，返回规范化后的路径和元数据
+    ///
+    /// 文件不存在、不是普通文件或通过符号链接逃逸出根目录时返回 `None`。
+    async fn locate(&self, key: &str) -> Result<Option<(PathBuf, Metadata)>, AppError> {
+        let Some(path) = self.resolve(key) else {
+            return Ok(None);
+        };
+
+        let canonical = match tokio::fs::canonicalize(&path).await {
+            Ok(canonical) => canonical,
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if !canonical.starts_with(&self.root) {
+            tracing::warn!(
+                "Refusing to serve '{}': escapes storage root",
+                path.display()
+            );
+            return Ok(None);
+        }
+
+        let metadata = tokio::fs::metadata(&canonical).await?;
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+        Ok(Some((canonical, metadata)))
+    }
+}
+
+#[async_trait]
+impl Storage for FsStorage {
+    /// 本地文件没有可供客户端直接访问的 URL
+    async fn get_presigned_url_with(
+        &self,
+        _key: &str,
+        _options: &PresignOptions,
+    ) -> Result<String, AppError> {
+        Err(AppError::PresignUnsupported)
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectMeta>, AppError> {
+        Ok(self
+            .locate(key)
+            .await?
+            .map(|(path, metadata)| object_meta(&path, &metadata)))
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        options: &GetObjectOptions,
+    ) -> Result<GetObjectOutput, AppError> {
+        let Some((path, metadata)) = self.locate(key).await? else {
+            return Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND));
+        };
+
+        match evaluate_conditions(options, &object_meta(&path, &metadata)) {
+            Precondition::Proceed => {}
+            Precondition::NotModified => {
+                return Ok(GetObjectOutput::empty(StatusCode::NOT_MODIFIED));
+            }
+            Precondition::Failed => {
+                return Ok(GetObjectOutput::empty(StatusCode::PRECONDITION_FAILED));
+            }
+        }
+
+        // 文件可能在 locate 之后被删除
+        let Some((meta, body)) = open_file(&path, options.range).await? else {
+            return Ok(GetObjectOutput::empty(StatusCode::NOT_FOUND));
+        };
+        let status = match options.range {
+            Some(_) => StatusCode::PARTIAL_CONTENT,
+            None => StatusCode::OK,
+        };
+        Ok(GetObjectOutput {
+            status,
+            meta,
+            range: options.range,
+            body,
+        })
+    }
+}
+
+/// 读取本地文件
+///
+/// # 参数
+///
+/// * `path` - 文件路径
+/// * `range` - 可选的字节区间（闭区间），调用方需保证区间位于文件大小之内
+///
+/// # 返回值
+///
+/// 文件的元数据和内容；文件不存在或不是普通文件时返回 `Ok(None)`
+async fn open_file(
+    path: &Path,
+    range: Option<(u64, u64)>,
+) -> Result<Option<(ObjectMeta, BoxStream<'static, Result<Bytes, AppError>>)>, AppError> {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) if is_not_found(&e) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let metadata = file.metadata().await?;
+    if !metadata.is_file() {
+        return Ok(None);
+    }
+
+    let meta = object_meta(path, &metadata);
+    let (start, len) = match range {
+        Some((start, end)) => (start, end - start + 1),
+        None => (0, meta.content_length),
+    };
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await?;
+    }
+
+    let body = ReaderStream::new(file.take(len))
+        .map_err(AppError::from)
+        .boxed();
+    Ok(Some((meta, body)))
+}
+
+/// 根据文件元数据构建对象元数据
+///
+/// ETag 由修改时间和文件大小生成（与 nginx 的做法一致）
+fn object_meta(path: &Path, metadata: &Metadata) -> ObjectMeta {
+    let last_modified = metadata.modified().ok();
+    let mtime = last_modified
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    ObjectMeta {
+        content_length: metadata.len(),
+        etag: Some(format!("\"{:x}-{:x}\"", mtime, metadata.len())),
+        last_modified,
+        content_type: MimeGuess::from_path(path).first().map(|m| m.to_string()),
+        content_encoding: None,
+        cache_control: None,
+        metadata: Default::default(),
+    }
+}
+
+fn is_not_found(e: &std::io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppState;
+    use axum::body::Body;
+    use axum::extract::State;
+    use axum::http::{Request, StatusCode};
+    use axum::response::IntoResponse;
+    use std::sync::Arc;
+
+    /// 创建测试目录：
+    /// - index.html
+    /// - app.js
+    /// - docs/index.html
+    fn site() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "<h1>root</h1>").unwrap();
+        std::fs::write(dir.path().join("app.js"), "console.log(1)").unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/index.html"), "<h1>docs</h1>").unwrap();
+        dir
+    }
+
+    async fn body_string(body: Body) -> String {
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    /// 测试存储键到本地路径的映射
+    ///
+    /// 验证：
+    /// - 带前缀的键映射到根目录下的文件
+    /// - 不带前缀、包含 `..` 或空段的键被拒绝
+    #[tokio::test]
+    async fn test_check_key_exists() {
+        let dir = site();
+        let storage = FsStorage::new(dir.path(), "www").unwrap();
+
+        assert!(storage.check_key_exists("www/index.html").await.unwrap());
+        assert!(
+            storage
+                .check_key_exists("www/docs/index.html")
+                .await
+                .unwrap()
+        );
+        assert!(!storage.check_key_exists("www/missing.html").await.unwrap());
+        assert!(!storage.check_key_exists("www/docs").await.unwrap());
+        assert!(!storage.check_key_exists("index.html").await.unwrap());
+        assert!(!storage.check_key_exists("www/../index.html").await.unwrap());
+        assert!(!storage.check_key_exists("www//index.html").await.unwrap());
+    }
+
+    /// 测试对象元数据
+    ///
+    /// 验证：
+    /// - 返回文件大小、ETag、修改时间和 Content-Type
+    #[tokio::test]
+    async fn test_head() {
+        let dir = site();
+        let storage = FsStorage::new(dir.path(), "www").unwrap();
+
+        let meta = storage.head("www/app.js").await.unwrap().unwrap();
+        assert_eq!(meta.content_length, 14);
+        assert!(meta.etag.unwrap().starts_with('"'));
+        assert!(meta.last_modified.is_some());
+        assert_eq!(meta.content_type.as_deref(), Some("text/javascript"));
+
+        assert_eq!(storage.head("www/missing.js").await.unwrap(), None);
+    }
+
+    /// 测试本地目录存储不支持预签名 URL
+    ///
+    /// 验证：
+    /// - 预签名返回 `AppError::PresignUnsupported`，而不是伪造的 URL
+    #[tokio::test]
+    async fn test_presign_unsupported() {
+        let dir = site();
+        let storage = FsStorage::new(dir.path(), "www").unwrap();
+
+        assert!(matches!(
+            storage
+                .get_presigned_url_with("www/app.js", &PresignOptions::default())
+                .await,
+            Err(AppError::PresignUnsupported)
+        ));
+    }
+
+    /// 测试拒绝逃逸出根目录的符号链接
+    ///
+    /// 验证：
+    /// - 指向根目录外的文件或目录的符号链接视为不存在
+    /// - 指向根目录内的符号链接正常使用
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlink_escape_refused() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+
+        let dir = site();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.txt"),
+            dir.path().join("secret.txt"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("outside")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("app.js"), dir.path().join("alias.js")).unwrap();
+
+        let storage = FsStorage::new(dir.path(), "www").unwrap();
+
+        assert!(!storage.check_key_exists("www/secret.txt").await.unwrap());
+        assert!(
+            !storage
+                .check_key_exists("www/outside/secret.txt")
+                .await
+                .unwrap()
+        );
+        assert!(storage.check_key_exists("www/alias.js").await.unwrap());
+    }
+
+    /// 测试 handle_files 使用本地文件系统存储
+    ///
+    /// 验证：
+    /// - 直接请求文件时返回文件内容和缓存头
+    /// - 缺失路径时通过 SPA 回退返回最近的 index.html
+    #[tokio::test]
+    async fn test_handle_files_with_fs_storage() {
+        let dir = site();
+        let state = AppState::new(
+            Arc::new(FsStorage::new(dir.path(), "www").unwrap()),
+            reqwest::Client::new(),
+        );
+
+        let request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let resp = crate::handlers::files::handle_files(State(state.clone()), request("/app.js"))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["content-type"], "text/javascript");
+        assert!(resp.headers().contains_key("cache-control"));
+        assert_eq!(body_string(resp.into_body()).await, "console.log(1)");
+
+        let resp = crate::handlers::files::handle_files(State(state), request("/docs/guide/intro"))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_string(resp.into_body()).await, "<h1>docs</h1>");
+    }
+}